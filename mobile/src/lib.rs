@@ -1,7 +1,14 @@
 use bevy::prelude::*;
+use bevy_rapier3d::prelude::*;
 use newtons_cradle::GamePlugin;
 
 #[bevy_main]
 fn main() {
-    App::new().add_plugins((DefaultPlugins, GamePlugin)).run()
+    App::new()
+        .add_plugins((
+            DefaultPlugins,
+            RapierPhysicsPlugin::<NoUserData>::default(),
+            GamePlugin,
+        ))
+        .run()
 }
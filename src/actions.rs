@@ -0,0 +1,377 @@
+use crate::{
+    camera::CameraView,
+    config::{load_config, save_config},
+};
+use bevy::{input::InputSystem, prelude::*, utils::HashSet};
+use serde::{Deserialize, Serialize};
+
+pub struct ActionsPlugin;
+
+const INPUT_CONFIG: &str = "input";
+
+/// This plugin maps mouse, keyboard, gamepad and touch input to game actions,
+/// the other plugins only ever ask the [`ActionState`] what the player wants to do
+impl Plugin for ActionsPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(load_config::<InputMap>(INPUT_CONFIG).unwrap_or_default())
+            .insert_resource(ActionState::default())
+            .add_systems(PreUpdate, update_action_state.after(InputSystem))
+            .add_systems(Update, save_input_map);
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Action {
+    /// pick up the ball under the pointer
+    Grab,
+    /// held while grabbing to add balls to the selection
+    MultiSelect,
+    /// drop every lifted ball at the same instant
+    Release,
+    /// put the cradle back into its starting position
+    Reset,
+    Pause,
+    /// advance the paused simulation by a single frame
+    StepFrame,
+    CameraOrbit,
+    CameraPan,
+    /// free camera, following a ball or jumping to impacts
+    CycleCameraMode,
+    /// switch to the next sky and environment lighting
+    NextEnvironment,
+    ToggleReleaseMode,
+    ToggleSettings,
+    SelectNextBall,
+    SelectPreviousBall,
+    /// switch between dragging and striking balls
+    CycleTool,
+    /// strike along the view ray or along the row of balls
+    ToggleHammerAxis,
+    /// shoot a projectile into the cradle
+    Fire,
+    /// fire from the camera or from the cannon
+    ToggleLaunchOrigin,
+    /// show or hide the slow motion inset
+    ToggleReplay,
+    ReplaySlower,
+    ReplayFaster,
+    ReplayPause,
+    /// go back to the cradle as it was before the last interaction
+    Undo,
+    Redo,
+    /// show or hide the paths the balls leave behind
+    ToggleTrails,
+    /// mark where and how hard balls hit each other
+    ToggleContacts,
+    /// show velocity, gravity and rope tension arrows on the balls
+    ToggleForces,
+    /// lift the ball with this index, counted from the left
+    LiftBall(u8),
+    /// fly the camera to a named viewpoint
+    CameraView(CameraView),
+}
+
+impl Action {
+    pub fn all() -> impl Iterator<Item = Action> {
+        [
+            Action::Grab,
+            Action::MultiSelect,
+            Action::Release,
+            Action::Reset,
+            Action::Pause,
+            Action::StepFrame,
+            Action::CameraOrbit,
+            Action::CameraPan,
+            Action::CycleCameraMode,
+            Action::NextEnvironment,
+            Action::ToggleReleaseMode,
+            Action::ToggleSettings,
+            Action::SelectNextBall,
+            Action::SelectPreviousBall,
+            Action::CycleTool,
+            Action::ToggleHammerAxis,
+            Action::Fire,
+            Action::ToggleLaunchOrigin,
+            Action::ToggleReplay,
+            Action::ReplaySlower,
+            Action::ReplayFaster,
+            Action::ReplayPause,
+            Action::Undo,
+            Action::Redo,
+            Action::ToggleTrails,
+            Action::ToggleContacts,
+            Action::ToggleForces,
+        ]
+        .into_iter()
+        .chain((0..9).map(Action::LiftBall))
+        .chain(CameraView::ALL.into_iter().map(Action::CameraView))
+    }
+
+    pub fn label(&self) -> String {
+        match self {
+            Action::Grab => "Grab".to_string(),
+            Action::MultiSelect => "Multi select".to_string(),
+            Action::Release => "Release".to_string(),
+            Action::Reset => "Reset".to_string(),
+            Action::Pause => "Pause".to_string(),
+            Action::StepFrame => "Step frame".to_string(),
+            Action::CameraOrbit => "Camera orbit".to_string(),
+            Action::CameraPan => "Camera pan".to_string(),
+            Action::CycleCameraMode => "Camera mode".to_string(),
+            Action::NextEnvironment => "Environment".to_string(),
+            Action::ToggleReleaseMode => "Release mode".to_string(),
+            Action::ToggleSettings => "Settings".to_string(),
+            Action::SelectNextBall => "Next ball".to_string(),
+            Action::SelectPreviousBall => "Previous ball".to_string(),
+            Action::CycleTool => "Switch tool".to_string(),
+            Action::ToggleHammerAxis => "Hammer axis".to_string(),
+            Action::Fire => "Fire".to_string(),
+            Action::ToggleLaunchOrigin => "Launch origin".to_string(),
+            Action::ToggleReplay => "Replay".to_string(),
+            Action::ReplaySlower => "Replay slower".to_string(),
+            Action::ReplayFaster => "Replay faster".to_string(),
+            Action::ReplayPause => "Replay pause".to_string(),
+            Action::Undo => "Undo".to_string(),
+            Action::Redo => "Redo".to_string(),
+            Action::ToggleTrails => "Trails".to_string(),
+            Action::ToggleContacts => "Contacts".to_string(),
+            Action::ToggleForces => "Forces".to_string(),
+            Action::LiftBall(index) => format!("Lift ball {}", index + 1),
+            Action::CameraView(view) => format!("{} view", view.label()),
+        }
+    }
+
+    /// Actions that keep working while gameplay input is suspended
+    fn is_ui(&self) -> bool {
+        matches!(self, Action::ToggleSettings)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum InputBinding {
+    Key(KeyCode),
+    /// the second key pressed while holding the first, e.g. Ctrl+Z
+    Chord(KeyCode, KeyCode),
+    Mouse(MouseButton),
+    Gamepad(GamepadButtonType),
+    /// a single finger on the touch screen
+    Touch,
+}
+
+impl InputBinding {
+    pub fn label(&self) -> String {
+        match self {
+            InputBinding::Key(key) => format!("{key:?}"),
+            InputBinding::Chord(modifier, key) => format!("{modifier:?}+{key:?}"),
+            InputBinding::Mouse(button) => format!("Mouse {button:?}"),
+            InputBinding::Gamepad(button) => format!("Pad {button:?}"),
+            InputBinding::Touch => "Touch".to_string(),
+        }
+    }
+}
+
+#[derive(Resource, Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct InputMap {
+    pub bindings: Vec<(Action, InputBinding)>,
+}
+
+impl Default for InputMap {
+    fn default() -> Self {
+        let mut bindings = vec![
+            (Action::Grab, InputBinding::Mouse(MouseButton::Left)),
+            (Action::Grab, InputBinding::Touch),
+            (Action::MultiSelect, InputBinding::Key(KeyCode::ShiftLeft)),
+            (Action::MultiSelect, InputBinding::Key(KeyCode::ShiftRight)),
+            (Action::Release, InputBinding::Key(KeyCode::Space)),
+            (
+                Action::Release,
+                InputBinding::Gamepad(GamepadButtonType::South),
+            ),
+            (Action::Reset, InputBinding::Key(KeyCode::R)),
+            (
+                Action::Reset,
+                InputBinding::Gamepad(GamepadButtonType::Select),
+            ),
+            (Action::Pause, InputBinding::Key(KeyCode::P)),
+            (
+                Action::Pause,
+                InputBinding::Gamepad(GamepadButtonType::Start),
+            ),
+            (Action::StepFrame, InputBinding::Key(KeyCode::Period)),
+            (Action::CameraOrbit, InputBinding::Mouse(MouseButton::Right)),
+            (Action::CameraPan, InputBinding::Mouse(MouseButton::Middle)),
+            (Action::CycleCameraMode, InputBinding::Key(KeyCode::V)),
+            (Action::NextEnvironment, InputBinding::Key(KeyCode::E)),
+            (Action::ToggleReleaseMode, InputBinding::Key(KeyCode::F)),
+            (Action::ToggleSettings, InputBinding::Key(KeyCode::Escape)),
+            (Action::CycleTool, InputBinding::Key(KeyCode::T)),
+            (Action::ToggleHammerAxis, InputBinding::Key(KeyCode::H)),
+            (Action::Fire, InputBinding::Key(KeyCode::G)),
+            (Action::Fire, InputBinding::Gamepad(GamepadButtonType::East)),
+            (Action::ToggleLaunchOrigin, InputBinding::Key(KeyCode::C)),
+            (Action::ToggleReplay, InputBinding::Key(KeyCode::I)),
+            (
+                Action::ReplaySlower,
+                InputBinding::Key(KeyCode::BracketLeft),
+            ),
+            (
+                Action::ReplayFaster,
+                InputBinding::Key(KeyCode::BracketRight),
+            ),
+            (Action::ReplayPause, InputBinding::Key(KeyCode::Backslash)),
+            (
+                Action::Undo,
+                InputBinding::Chord(KeyCode::ControlLeft, KeyCode::Z),
+            ),
+            (
+                Action::Undo,
+                InputBinding::Chord(KeyCode::ControlRight, KeyCode::Z),
+            ),
+            (
+                Action::Redo,
+                InputBinding::Chord(KeyCode::ControlLeft, KeyCode::Y),
+            ),
+            (
+                Action::Redo,
+                InputBinding::Chord(KeyCode::ControlRight, KeyCode::Y),
+            ),
+            (
+                Action::SelectNextBall,
+                InputBinding::Gamepad(GamepadButtonType::RightTrigger),
+            ),
+            (
+                Action::SelectPreviousBall,
+                InputBinding::Gamepad(GamepadButtonType::LeftTrigger),
+            ),
+            (Action::ToggleTrails, InputBinding::Key(KeyCode::F5)),
+            (Action::ToggleContacts, InputBinding::Key(KeyCode::F6)),
+            (Action::ToggleForces, InputBinding::Key(KeyCode::F7)),
+        ];
+        let number_keys = [
+            KeyCode::Key1,
+            KeyCode::Key2,
+            KeyCode::Key3,
+            KeyCode::Key4,
+            KeyCode::Key5,
+            KeyCode::Key6,
+            KeyCode::Key7,
+            KeyCode::Key8,
+            KeyCode::Key9,
+        ];
+        for (index, key) in number_keys.into_iter().enumerate() {
+            bindings.push((Action::LiftBall(index as u8), InputBinding::Key(key)));
+        }
+        let view_bindings = [
+            (CameraView::Front, KeyCode::F1, GamepadButtonType::DPadDown),
+            (CameraView::Side, KeyCode::F2, GamepadButtonType::DPadLeft),
+            (CameraView::TopDown, KeyCode::F3, GamepadButtonType::DPadUp),
+            (
+                CameraView::Impact,
+                KeyCode::F4,
+                GamepadButtonType::DPadRight,
+            ),
+        ];
+        for (view, key, button) in view_bindings {
+            bindings.push((Action::CameraView(view), InputBinding::Key(key)));
+            bindings.push((Action::CameraView(view), InputBinding::Gamepad(button)));
+        }
+        InputMap { bindings }
+    }
+}
+
+impl InputMap {
+    pub fn is_bound(&self, action: Action, binding: InputBinding) -> bool {
+        self.bindings.contains(&(action, binding))
+    }
+}
+
+#[derive(Resource, Default)]
+pub struct ActionState {
+    pressed: HashSet<Action>,
+    just_pressed: HashSet<Action>,
+    just_released: HashSet<Action>,
+    /// only ui actions are reported while this is set, e.g. when the settings are open
+    pub suspend_gameplay: bool,
+}
+
+impl ActionState {
+    pub fn pressed(&self, action: Action) -> bool {
+        self.pressed.contains(&action)
+    }
+
+    pub fn just_pressed(&self, action: Action) -> bool {
+        self.just_pressed.contains(&action)
+    }
+
+    pub fn just_released(&self, action: Action) -> bool {
+        self.just_released.contains(&action)
+    }
+
+    /// Treats `action` as already held, so input used by the ui does not trigger it again
+    pub fn consume(&mut self, action: Action) {
+        self.just_pressed.remove(&action);
+        self.pressed.insert(action);
+    }
+}
+
+fn update_action_state(
+    input_map: Res<InputMap>,
+    keys: Res<Input<KeyCode>>,
+    mouse_buttons: Res<Input<MouseButton>>,
+    gamepads: Res<Gamepads>,
+    gamepad_buttons: Res<Input<GamepadButton>>,
+    touches: Res<Touches>,
+    mut action_state: ResMut<ActionState>,
+    // modifier and key of the chords held down, in that order
+    mut held_chords: Local<HashSet<(KeyCode, KeyCode)>>,
+) {
+    let single_touch = touches.iter().count() == 1;
+
+    // a chord starts when its key goes down while the modifier is held,
+    // holding the key first and then the modifier is not the chord
+    let chords: HashSet<(KeyCode, KeyCode)> = input_map
+        .bindings
+        .iter()
+        .filter_map(|(_, binding)| match binding {
+            InputBinding::Chord(modifier, key) => Some((*modifier, *key)),
+            _ => None,
+        })
+        .filter(|(modifier, key)| {
+            keys.pressed(*modifier)
+                && (keys.just_pressed(*key)
+                    || keys.pressed(*key) && held_chords.contains(&(*modifier, *key)))
+        })
+        .collect();
+    *held_chords = chords;
+
+    let mut pressed = HashSet::default();
+    for (action, binding) in &input_map.bindings {
+        if action_state.suspend_gameplay && !action.is_ui() {
+            continue;
+        }
+
+        let is_pressed = match binding {
+            InputBinding::Key(key) => keys.pressed(*key),
+            InputBinding::Chord(modifier, key) => held_chords.contains(&(*modifier, *key)),
+            InputBinding::Mouse(button) => mouse_buttons.pressed(*button),
+            InputBinding::Gamepad(button_type) => gamepads
+                .iter()
+                .any(|gamepad| gamepad_buttons.pressed(GamepadButton::new(gamepad, *button_type))),
+            InputBinding::Touch => single_touch,
+        };
+        if is_pressed {
+            pressed.insert(*action);
+        }
+    }
+
+    let action_state = &mut *action_state;
+    action_state.just_pressed = pressed.difference(&action_state.pressed).copied().collect();
+    action_state.just_released = action_state.pressed.difference(&pressed).copied().collect();
+    action_state.pressed = pressed;
+}
+
+fn save_input_map(input_map: Res<InputMap>) {
+    if input_map.is_changed() && !input_map.is_added() {
+        save_config(INPUT_CONFIG, &*input_map);
+    }
+}
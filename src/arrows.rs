@@ -0,0 +1,21 @@
+use bevy::prelude::*;
+
+// Gizmos have lines but no arrows, these are shared by the overlays.
+
+/// Draws a line from `start` to `end` with a small head at `end`
+pub fn draw_arrow(gizmos: &mut Gizmos, start: Vec3, end: Vec3, color: Color) {
+    let shaft = end - start;
+    let length = shaft.length();
+    if length < f32::EPSILON {
+        return;
+    }
+    gizmos.line(start, end, color);
+
+    let direction = shaft / length;
+    // any direction across the shaft works for the two barbs
+    let across = direction.any_orthonormal_vector();
+    let head = (length * 0.2).min(0.4);
+    for side in [across, -across] {
+        gizmos.line(end, end - direction * head + side * head * 0.5, color);
+    }
+}
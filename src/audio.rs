@@ -11,6 +11,7 @@ pub struct InternalAudioPlugin;
 impl Plugin for InternalAudioPlugin {
     fn build(&self, app: &mut App) {
         app.insert_resource(BallSoundMeta::default())
+            .add_event::<BallImpact>()
             .add_plugins(AudioPlugin)
             .add_systems(
                 Update,
@@ -27,26 +28,42 @@ struct BallSoundMeta {
 #[derive(Component, Default)]
 pub struct BallSound {}
 
+/// Two bodies started touching, sent with every collision the sound is picked from
+#[derive(Event, Clone, Copy, Debug)]
+pub struct BallImpact {
+    /// halfway between the two bodies
+    pub position: Vec3,
+    /// relative speed of the two bodies
+    pub speed: f32,
+}
+
 fn handle_ball_impact_sounds(
     mut collision_events: EventReader<CollisionEvent>,
     audio_assets: Res<AudioAssets>,
     audio: Res<Audio>,
-    velocities: Query<&Velocity>,
+    velocities: Query<(&Velocity, &GlobalTransform)>,
     time: Res<Time>,
     mut ball_sound_meta: ResMut<BallSoundMeta>,
+    mut impacts: EventWriter<BallImpact>,
 ) {
     //only one sound per frame
     let mut max_volume = 0.0;
     for event in collision_events.iter() {
-        let (entity_a, entity_b, _ongoing) = unpack_collision_event(event);
+        let (entity_a, entity_b, started) = unpack_collision_event(event);
 
-        if let Ok(velocity_a) = velocities.get(entity_a) {
-            if let Ok(velocity_b) = velocities.get(entity_b) {
+        if let Ok((velocity_a, transform_a)) = velocities.get(entity_a) {
+            if let Ok((velocity_b, transform_b)) = velocities.get(entity_b) {
                 let rel_velocity = (velocity_a.linvel - velocity_b.linvel).abs();
                 let volume = (rel_velocity.length() / 10.0).clamp(0.0, 1.0) as f64;
                 if volume > max_volume {
                     max_volume = volume;
                 }
+                if started {
+                    impacts.send(BallImpact {
+                        position: (transform_a.translation() + transform_b.translation()) / 2.0,
+                        speed: rel_velocity.length(),
+                    });
+                }
             }
         }
     }
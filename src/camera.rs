@@ -0,0 +1,517 @@
+use crate::{
+    actions::{Action, ActionState},
+    audio::BallImpact,
+    cradle::Ball,
+    scene::GROUND_HEIGHT,
+    GameState,
+};
+use bevy::{
+    input::mouse::{MouseMotion, MouseScrollUnit, MouseWheel},
+    prelude::*,
+};
+use serde::{Deserialize, Serialize};
+use std::f32::consts::{FRAC_PI_2, PI, TAU};
+
+pub struct CameraControlPlugin;
+
+/// This plugin moves the camera around the cradle from user input
+impl Plugin for CameraControlPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(PinchGesture::default())
+            .insert_resource(CameraMode::default())
+            .insert_resource(CameraFollowSettings::default())
+            .add_event::<SelectCameraView>()
+            .add_systems(
+                Update,
+                (
+                    handle_touch_gestures.run_if(in_state(GameState::Playing)),
+                    handle_mouse_orbit.run_if(in_state(GameState::Playing)),
+                    handle_mouse_wheel.run_if(in_state(GameState::Playing)),
+                    handle_camera_view_keys.run_if(in_state(GameState::Playing)),
+                    cycle_camera_mode.run_if(in_state(GameState::Playing)),
+                    follow_ball.run_if(in_state(GameState::Playing)),
+                    track_impacts.run_if(in_state(GameState::Playing)),
+                    start_camera_transition,
+                    animate_camera_transition,
+                    apply_orbit_camera,
+                )
+                    .chain(),
+            );
+    }
+}
+
+/// The camera the player looks through, picking and launching go through it
+#[derive(Component, Default)]
+pub struct MainCamera {}
+
+/// Places the camera on a sphere around `focus`
+#[derive(Component, Clone, Copy, Debug)]
+pub struct OrbitCamera {
+    pub focus: Vec3,
+    pub yaw: f32,
+    pub pitch: f32,
+    pub distance: f32,
+    pub min_distance: f32,
+    pub max_distance: f32,
+    /// panning keeps `focus` within `max_pan` of this point
+    pub center: Vec3,
+    pub max_pan: f32,
+    /// the camera never goes below this height, so it stays above the ground
+    pub min_height: f32,
+}
+
+impl OrbitCamera {
+    pub fn looking_at(eye: Vec3, focus: Vec3) -> Self {
+        let offset = eye - focus;
+        let distance = offset.length();
+        OrbitCamera {
+            focus,
+            yaw: offset.x.atan2(offset.z),
+            pitch: (offset.y / distance).asin(),
+            distance,
+            min_distance: 5.0,
+            max_distance: 150.0,
+            center: focus,
+            max_pan: 30.0,
+            min_height: GROUND_HEIGHT + 1.0,
+        }
+    }
+
+    pub fn eye(&self) -> Vec3 {
+        let (yaw_sin, yaw_cos) = self.yaw.sin_cos();
+        let (pitch_sin, pitch_cos) = self.pitch.sin_cos();
+        self.focus + Vec3::new(pitch_cos * yaw_sin, pitch_sin, pitch_cos * yaw_cos) * self.distance
+    }
+
+    pub fn orbit(&mut self, yaw: f32, pitch: f32) {
+        self.yaw += yaw;
+        self.pitch = (self.pitch + pitch).clamp(-1.5, 1.5);
+    }
+
+    pub fn zoom(&mut self, factor: f32) {
+        self.distance = (self.distance * factor).clamp(self.min_distance, self.max_distance);
+    }
+
+    /// Moves the focus in the view plane, `delta` is in units of the orbit distance
+    pub fn pan(&mut self, delta: Vec2) {
+        let forward = (self.focus - self.eye()).normalize_or_zero();
+        let right = forward.cross(Vec3::Y).normalize_or_zero();
+        let up = right.cross(forward);
+        let focus = self.focus + (right * delta.x + up * delta.y) * self.distance;
+
+        let offset = (focus - self.center).clamp_length_max(self.max_pan);
+        self.focus = self.center + offset;
+        self.focus.y = self.focus.y.max(self.min_height);
+    }
+
+    /// Raises the pitch until the eye is at least at `min_height`
+    fn keep_above_ground(&mut self) {
+        let lowest_sin = (self.min_height - self.focus.y) / self.distance;
+        if self.pitch.sin() < lowest_sin {
+            self.pitch = lowest_sin.clamp(-1.0, 1.0).asin();
+        }
+    }
+}
+
+/// Named viewpoints the camera can fly to
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum CameraView {
+    Front,
+    /// along the row of balls, shows the gaps between them
+    Side,
+    TopDown,
+    /// close to where the first ball hits the second
+    Impact,
+}
+
+impl CameraView {
+    pub const ALL: [CameraView; 4] = [
+        CameraView::Front,
+        CameraView::Side,
+        CameraView::TopDown,
+        CameraView::Impact,
+    ];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            CameraView::Front => "Front",
+            CameraView::Side => "Side",
+            CameraView::TopDown => "Top",
+            CameraView::Impact => "Impact",
+        }
+    }
+
+    /// Where the camera ends up, `balls` are the ball positions ordered by index
+    fn target(&self, current: &OrbitCamera, balls: &[Vec3]) -> OrbitCamera {
+        let mut target = *current;
+        target.focus = current.center;
+        match self {
+            CameraView::Front => {
+                target.yaw = 0.0;
+                target.pitch = 0.15;
+                target.distance = 40.0;
+            }
+            CameraView::Side => {
+                target.yaw = FRAC_PI_2;
+                target.pitch = 0.05;
+                target.distance = 35.0;
+            }
+            CameraView::TopDown => {
+                target.yaw = 0.0;
+                target.pitch = 1.5;
+                target.distance = 45.0;
+            }
+            CameraView::Impact => {
+                if let [first, second, ..] = balls {
+                    target.focus = (*first + *second) / 2.0;
+                }
+                target.yaw = 0.3;
+                target.pitch = 0.1;
+                target.distance = target.min_distance.max(8.0);
+            }
+        }
+        target
+    }
+}
+
+/// Sent to fly the camera to a viewpoint
+#[derive(Event)]
+pub struct SelectCameraView(pub CameraView);
+
+/// Eases the orbit camera from one placement to another
+#[derive(Component)]
+pub struct CameraTransition {
+    from: OrbitCamera,
+    to: OrbitCamera,
+    elapsed: f32,
+    /// seconds
+    duration: f32,
+}
+
+impl CameraTransition {
+    pub fn new(from: OrbitCamera, to: OrbitCamera) -> Self {
+        CameraTransition {
+            from,
+            to,
+            elapsed: 0.0,
+            duration: 0.8,
+        }
+    }
+
+    pub fn with_duration(mut self, duration: f32) -> Self {
+        self.duration = duration;
+        self
+    }
+
+    fn sample(&self) -> OrbitCamera {
+        let t = (self.elapsed / self.duration).clamp(0.0, 1.0);
+        // cubic ease in and out
+        let t = if t < 0.5 {
+            4.0 * t * t * t
+        } else {
+            1.0 - (-2.0 * t + 2.0).powi(3) / 2.0
+        };
+
+        // turn the short way round
+        let yaw_delta = (self.to.yaw - self.from.yaw + PI).rem_euclid(TAU) - PI;
+
+        let mut orbit = self.to;
+        orbit.focus = self.from.focus.lerp(self.to.focus, t);
+        orbit.yaw = self.from.yaw + yaw_delta * t;
+        orbit.pitch = self.from.pitch + (self.to.pitch - self.from.pitch) * t;
+        orbit.distance = self.from.distance + (self.to.distance - self.from.distance) * t;
+        orbit
+    }
+
+    fn finished(&self) -> bool {
+        self.elapsed >= self.duration
+    }
+}
+
+fn handle_camera_view_keys(
+    actions: Res<ActionState>,
+    mut camera_views: EventWriter<SelectCameraView>,
+) {
+    for view in CameraView::ALL {
+        if actions.just_pressed(Action::CameraView(view)) {
+            camera_views.send(SelectCameraView(view));
+        }
+    }
+}
+
+/// What moves the camera besides the player
+#[derive(Resource, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum CameraMode {
+    #[default]
+    Free,
+    /// keep the ball with this index in the middle of the view
+    FollowBall(usize),
+    /// cut to the most recent hard collision
+    TrackImpacts,
+}
+
+impl CameraMode {
+    /// Steps through free, following each ball and tracking impacts
+    pub fn cycle(&self, direction: i32, ball_count: usize) -> CameraMode {
+        let modes: Vec<_> = std::iter::once(CameraMode::Free)
+            .chain((0..ball_count).map(CameraMode::FollowBall))
+            .chain(std::iter::once(CameraMode::TrackImpacts))
+            .collect();
+        let current = modes.iter().position(|mode| mode == self).unwrap_or(0) as i32;
+        modes[(current + direction).rem_euclid(modes.len() as i32) as usize]
+    }
+
+    pub fn label(&self) -> String {
+        match self {
+            CameraMode::Free => "Free".to_string(),
+            CameraMode::FollowBall(index) => format!("Follow ball {}", index + 1),
+            CameraMode::TrackImpacts => "Track impacts".to_string(),
+        }
+    }
+}
+
+#[derive(Resource)]
+pub struct CameraFollowSettings {
+    /// how quickly the focus catches up with a followed ball, per second
+    pub follow_rate: f32,
+    /// collisions slower than this are not worth a cut
+    pub impact_speed: f32,
+    pub impact_distance: f32,
+    /// seconds of the cut to an impact
+    pub cut_duration: f32,
+    /// seconds the camera stays on an impact before it cuts to the next
+    pub min_cut_interval: f32,
+}
+
+impl Default for CameraFollowSettings {
+    fn default() -> Self {
+        CameraFollowSettings {
+            follow_rate: 6.0,
+            impact_speed: 4.0,
+            impact_distance: 12.0,
+            cut_duration: 0.25,
+            min_cut_interval: 1.0,
+        }
+    }
+}
+
+fn cycle_camera_mode(
+    actions: Res<ActionState>,
+    mut camera_mode: ResMut<CameraMode>,
+    balls: Query<&Ball>,
+) {
+    if actions.just_pressed(Action::CycleCameraMode) {
+        *camera_mode = camera_mode.cycle(1, balls.iter().count());
+        info!("camera mode: {}", camera_mode.label());
+    }
+}
+
+fn follow_ball(
+    time: Res<Time>,
+    camera_mode: Res<CameraMode>,
+    follow_settings: Res<CameraFollowSettings>,
+    balls: Query<(&Ball, &Transform)>,
+    mut cameras: Query<&mut OrbitCamera, Without<CameraTransition>>,
+) {
+    let CameraMode::FollowBall(index) = *camera_mode else {
+        return;
+    };
+    let Some((_, ball_transform)) = balls.iter().find(|(ball, _)| ball.index == index) else {
+        return;
+    };
+
+    let blend = 1.0 - (-follow_settings.follow_rate * time.delta_seconds()).exp();
+    for mut orbit in &mut cameras {
+        orbit.focus = orbit.focus.lerp(ball_transform.translation, blend);
+    }
+}
+
+fn track_impacts(
+    mut commands: Commands,
+    time: Res<Time>,
+    camera_mode: Res<CameraMode>,
+    follow_settings: Res<CameraFollowSettings>,
+    mut impacts: EventReader<BallImpact>,
+    mut last_cut: Local<f32>,
+    cameras: Query<(Entity, &OrbitCamera)>,
+) {
+    let hardest = impacts
+        .iter()
+        .filter(|impact| impact.speed >= follow_settings.impact_speed)
+        .max_by(|a, b| a.speed.total_cmp(&b.speed))
+        .copied();
+    if *camera_mode != CameraMode::TrackImpacts {
+        return;
+    }
+    let Some(impact) = hardest else {
+        return;
+    };
+
+    let now = time.elapsed_seconds();
+    if now - *last_cut < follow_settings.min_cut_interval {
+        return;
+    }
+    *last_cut = now;
+
+    for (entity, orbit) in &cameras {
+        let mut target = *orbit;
+        target.focus = impact.position;
+        target.distance = follow_settings
+            .impact_distance
+            .clamp(orbit.min_distance, orbit.max_distance);
+        commands.entity(entity).insert(
+            CameraTransition::new(*orbit, target).with_duration(follow_settings.cut_duration),
+        );
+    }
+}
+
+fn start_camera_transition(
+    mut commands: Commands,
+    mut camera_mode: ResMut<CameraMode>,
+    mut camera_views: EventReader<SelectCameraView>,
+    balls: Query<(&Ball, &Transform)>,
+    cameras: Query<(Entity, &OrbitCamera)>,
+) {
+    let Some(SelectCameraView(view)) = camera_views.iter().last() else {
+        return;
+    };
+    // a fixed viewpoint stops following balls or impacts
+    camera_mode.set_if_neq(CameraMode::Free);
+
+    let mut ball_positions: Vec<_> = balls.iter().collect();
+    ball_positions.sort_by_key(|(ball, _)| ball.index);
+    let ball_positions: Vec<_> = ball_positions
+        .into_iter()
+        .map(|(_, transform)| transform.translation)
+        .collect();
+
+    for (entity, orbit) in &cameras {
+        let target = view.target(orbit, &ball_positions);
+        commands
+            .entity(entity)
+            .insert(CameraTransition::new(*orbit, target));
+    }
+}
+
+fn animate_camera_transition(
+    mut commands: Commands,
+    time: Res<Time>,
+    actions: Res<ActionState>,
+    mut cameras: Query<(Entity, &mut OrbitCamera, &mut CameraTransition)>,
+) {
+    // grabbing the camera by hand takes over from the transition
+    let manual = actions.pressed(Action::CameraOrbit) || actions.pressed(Action::CameraPan);
+
+    for (entity, mut orbit, mut transition) in &mut cameras {
+        transition.elapsed += time.delta_seconds();
+        if !manual {
+            *orbit = transition.sample();
+        }
+        if manual || transition.finished() {
+            commands.entity(entity).remove::<CameraTransition>();
+        }
+    }
+}
+
+/// Places the camera, after everything that moves it, so none of them can take it below the ground
+fn apply_orbit_camera(
+    mut cameras: Query<(&mut OrbitCamera, &mut Transform), Changed<OrbitCamera>>,
+) {
+    for (mut orbit, mut transform) in &mut cameras {
+        // already being handled, no need to be picked up as changed again next frame
+        let orbit = orbit.bypass_change_detection();
+        orbit.keep_above_ground();
+        *transform = Transform::from_translation(orbit.eye()).looking_at(orbit.focus, Vec3::Y);
+    }
+}
+
+/// radians per pixel of mouse movement
+const ORBIT_SENSITIVITY: f32 = 0.005;
+/// orbit distances per pixel of mouse movement
+const PAN_SENSITIVITY: f32 = 0.001;
+/// zoom factor per line of the mouse wheel
+const ZOOM_PER_LINE: f32 = 0.9;
+/// pixels of a touchpad scroll that count as one line
+const PIXELS_PER_LINE: f32 = 50.0;
+
+/// Right drag orbits and middle drag pans, the left button stays free for the balls
+fn handle_mouse_orbit(
+    actions: Res<ActionState>,
+    mut motion_evr: EventReader<MouseMotion>,
+    mut cameras: Query<&mut OrbitCamera>,
+) {
+    let delta: Vec2 = motion_evr.iter().map(|ev| ev.delta).sum();
+    if delta == Vec2::ZERO {
+        return;
+    }
+
+    for mut orbit in &mut cameras {
+        if actions.pressed(Action::CameraOrbit) {
+            orbit.orbit(-delta.x * ORBIT_SENSITIVITY, delta.y * ORBIT_SENSITIVITY);
+        } else if actions.pressed(Action::CameraPan) {
+            orbit.pan(Vec2::new(-delta.x, delta.y) * PAN_SENSITIVITY);
+        }
+    }
+}
+
+fn handle_mouse_wheel(
+    actions: Res<ActionState>,
+    mut wheel_evr: EventReader<MouseWheel>,
+    mut cameras: Query<&mut OrbitCamera>,
+) {
+    let lines: f32 = wheel_evr
+        .iter()
+        .map(|ev| match ev.unit {
+            MouseScrollUnit::Line => ev.y,
+            MouseScrollUnit::Pixel => ev.y / PIXELS_PER_LINE,
+        })
+        .sum();
+    // while the settings panel is open the wheel scrolls it instead, see `scroll_settings_panel`
+    if lines == 0.0 || actions.suspend_gameplay {
+        return;
+    }
+
+    for mut orbit in &mut cameras {
+        orbit.zoom(ZOOM_PER_LINE.powf(lines));
+    }
+}
+
+#[derive(Resource, Default)]
+struct PinchGesture {
+    last: Option<(u64, Vec2, u64, Vec2)>,
+}
+
+/// Two fingers pinch to zoom and twist to rotate around the cradle
+fn handle_touch_gestures(
+    actions: Res<ActionState>,
+    touches: Res<Touches>,
+    mut pinch: ResMut<PinchGesture>,
+    mut cameras: Query<&mut OrbitCamera>,
+) {
+    let mut pressed: Vec<_> = touches.iter().collect();
+    // while the settings panel is open a finger scrolls it instead, see `scroll_settings_panel`
+    if pressed.len() != 2 || actions.suspend_gameplay {
+        pinch.last = None;
+        return;
+    }
+    pressed.sort_by_key(|touch| touch.id());
+    let current = (
+        pressed[0].id(),
+        pressed[0].position(),
+        pressed[1].id(),
+        pressed[1].position(),
+    );
+
+    if let Some((last_id_a, last_a, last_id_b, last_b)) = pinch.last {
+        let span = current.3 - current.1;
+        let last_span = last_b - last_a;
+        let same_fingers = last_id_a == current.0 && last_id_b == current.2;
+        if same_fingers && span.length() > f32::EPSILON && last_span.length() > f32::EPSILON {
+            for mut orbit in &mut cameras {
+                orbit.zoom(last_span.length() / span.length());
+                orbit.yaw -= last_span.angle_between(span);
+            }
+        }
+    }
+    pinch.last = Some(current);
+}
@@ -0,0 +1,27 @@
+use bevy_rapier3d::prelude::*;
+
+// Collision group layout shared by everything in the scene that has a collider
+// or queries for one.
+pub const BALL_GROUP: Group = Group::GROUP_1;
+pub const ANCHOR_GROUP: Group = Group::GROUP_2;
+pub const GROUND_GROUP: Group = Group::GROUP_3;
+pub const CURSOR_GROUP: Group = Group::GROUP_4;
+
+/// Balls hit each other and the ground, and are the only thing the cursor can pick
+pub fn ball_groups() -> CollisionGroups {
+    CollisionGroups::new(BALL_GROUP, BALL_GROUP | GROUND_GROUP | CURSOR_GROUP)
+}
+
+/// Anchors only hold the ropes, they should never be touched by anything
+pub fn anchor_groups() -> CollisionGroups {
+    CollisionGroups::new(ANCHOR_GROUP, Group::NONE)
+}
+
+pub fn ground_groups() -> CollisionGroups {
+    CollisionGroups::new(GROUND_GROUP, BALL_GROUP)
+}
+
+/// Used by the cursor ray cast when picking
+pub fn cursor_groups() -> CollisionGroups {
+    CollisionGroups::new(CURSOR_GROUP, BALL_GROUP)
+}
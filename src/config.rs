@@ -0,0 +1,48 @@
+use bevy::prelude::*;
+use serde::{de::DeserializeOwned, Serialize};
+
+// Settings are stored as RON files in a `config` folder of the working directory.
+// The web build has no file system, so it always starts from the defaults.
+#[cfg(not(target_arch = "wasm32"))]
+const CONFIG_DIR: &str = "config";
+
+#[cfg(not(target_arch = "wasm32"))]
+fn config_path(name: &str) -> std::path::PathBuf {
+    std::path::Path::new(CONFIG_DIR).join(format!("{name}.ron"))
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+pub fn load_config<T: DeserializeOwned>(name: &str) -> Option<T> {
+    let path = config_path(name);
+    let contents = std::fs::read_to_string(&path).ok()?;
+    match ron::from_str(&contents) {
+        Ok(config) => Some(config),
+        Err(err) => {
+            warn!("ignoring {}: {}", path.display(), err);
+            None
+        }
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+pub fn load_config<T: DeserializeOwned>(_name: &str) -> Option<T> {
+    None
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+pub fn save_config<T: Serialize>(name: &str, config: &T) {
+    let path = config_path(name);
+    let result = ron::ser::to_string_pretty(config, ron::ser::PrettyConfig::default())
+        .map_err(|err| err.to_string())
+        .and_then(|contents| {
+            std::fs::create_dir_all(CONFIG_DIR)
+                .and_then(|_| std::fs::write(&path, contents))
+                .map_err(|err| err.to_string())
+        });
+    if let Err(err) = result {
+        warn!("could not save {}: {}", path.display(), err);
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+pub fn save_config<T: Serialize>(_name: &str, _config: &T) {}
@@ -0,0 +1,172 @@
+use crate::{
+    actions::{Action, ActionState},
+    arrows::draw_arrow,
+    cradle::Ball,
+    GameState,
+};
+use bevy::prelude::*;
+use bevy_rapier3d::prelude::*;
+
+pub struct ContactsPlugin;
+
+/// This plugin marks where balls hit each other, with the contact normal and the
+/// impulse of the hit as arrows that fade out. Hits of the same physics step share
+/// a color, so simultaneous collisions can be told from sequential ones
+impl Plugin for ContactsPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(ContactSettings::default())
+            .insert_resource(ContactMarks::default())
+            .add_systems(
+                Update,
+                (
+                    toggle_contacts.run_if(in_state(GameState::Playing)),
+                    record_contacts.run_if(in_state(GameState::Playing)),
+                    draw_contacts.run_if(in_state(GameState::Playing)),
+                )
+                    .chain(),
+            );
+    }
+}
+
+#[derive(Resource, Clone, Debug, PartialEq)]
+pub struct ContactSettings {
+    pub visible: bool,
+    /// seconds until a mark is gone
+    pub fade: f32,
+    /// arrow length per unit of impulse
+    pub impulse_scale: f32,
+}
+
+impl Default for ContactSettings {
+    fn default() -> Self {
+        ContactSettings {
+            visible: false,
+            fade: 1.0,
+            impulse_scale: 0.02,
+        }
+    }
+}
+
+struct ContactMark {
+    time: f32,
+    point: Vec3,
+    /// points from the first ball into the second
+    normal: Vec3,
+    impulse: f32,
+    color: Color,
+}
+
+#[derive(Resource, Default)]
+struct ContactMarks {
+    marks: Vec<ContactMark>,
+    /// counts the frames with hits, each gets the next color
+    steps: u32,
+}
+
+fn toggle_contacts(actions: Res<ActionState>, mut contact_settings: ResMut<ContactSettings>) {
+    if actions.just_pressed(Action::ToggleContacts) {
+        contact_settings.visible = !contact_settings.visible;
+    }
+}
+
+fn record_contacts(
+    time: Res<Time>,
+    contact_settings: Res<ContactSettings>,
+    rapier_context: Res<RapierContext>,
+    mut collision_events: EventReader<CollisionEvent>,
+    mut contact_marks: ResMut<ContactMarks>,
+    balls: Query<&GlobalTransform, With<Ball>>,
+) {
+    let now = time.elapsed_seconds();
+    contact_marks
+        .marks
+        .retain(|mark| now - mark.time < contact_settings.fade);
+    if !contact_settings.visible {
+        collision_events.clear();
+        return;
+    }
+
+    // a golden angle apart, neighbouring steps never look alike
+    let hue = (contact_marks.steps as f32 * 137.5) % 360.0;
+    let color = Color::hsl(hue, 0.9, 0.6);
+    let mut marked = false;
+    for event in collision_events.iter() {
+        let CollisionEvent::Started(first, second, _) = event else {
+            continue;
+        };
+        let (Ok(first_transform), Ok(second_transform)) = (balls.get(*first), balls.get(*second))
+        else {
+            continue;
+        };
+        let Some(pair) = rapier_context.contact_pair(*first, *second) else {
+            continue;
+        };
+        // rapier keeps the pair in its own order, which need not be the one of the event
+        let swapped = pair.collider1() == *second;
+        let (collider1_transform, direction) = if swapped {
+            (second_transform, -1.0)
+        } else {
+            (first_transform, 1.0)
+        };
+
+        for manifold in pair.manifolds() {
+            let normal = manifold.normal() * direction;
+            let impulse: f32 = manifold.points().map(|contact| contact.impulse()).sum();
+            let points: Vec<Vec3> = if manifold.num_solver_contacts() > 0 {
+                manifold
+                    .solver_contacts()
+                    .map(|contact| contact.point())
+                    .collect()
+            } else {
+                manifold
+                    .points()
+                    .map(|contact| {
+                        // rapier points are already scaled, only move and turn them
+                        let (_, rotation, translation) =
+                            collider1_transform.to_scale_rotation_translation();
+                        translation + rotation * contact.local_p1()
+                    })
+                    .collect()
+            };
+            for point in points {
+                contact_marks.marks.push(ContactMark {
+                    time: now,
+                    point,
+                    normal,
+                    impulse,
+                    color,
+                });
+                marked = true;
+            }
+        }
+    }
+    if marked {
+        contact_marks.steps += 1;
+    }
+}
+
+fn draw_contacts(
+    time: Res<Time>,
+    contact_settings: Res<ContactSettings>,
+    contact_marks: Res<ContactMarks>,
+    mut gizmos: Gizmos,
+) {
+    if !contact_settings.visible {
+        return;
+    }
+    let now = time.elapsed_seconds();
+    for mark in &contact_marks.marks {
+        let alpha = 1.0 - ((now - mark.time) / contact_settings.fade).clamp(0.0, 1.0);
+        let color = mark.color.with_a(alpha);
+        gizmos.sphere(mark.point, Quat::IDENTITY, 0.1, color);
+        gizmos.line(
+            mark.point,
+            mark.point + mark.normal,
+            Color::WHITE.with_a(alpha),
+        );
+        // equal and opposite, one arrow into each ball
+        let impulse = mark.normal * mark.impulse * contact_settings.impulse_scale;
+        draw_arrow(&mut gizmos, mark.point, mark.point + impulse, color);
+        draw_arrow(&mut gizmos, mark.point, mark.point - impulse, color);
+    }
+}
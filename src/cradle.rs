@@ -1,4 +1,13 @@
-use crate::{GameState, audio::BallSound};
+use crate::{
+    actions::{Action, ActionState},
+    audio::BallSound,
+    collision::{anchor_groups, ball_groups},
+    graphics::SphereMesh,
+    history::RecordHistory,
+    interaction::Grabbable,
+    models::{spawn_cradle_model, SceneModels},
+    GameState,
+};
 use bevy::prelude::*;
 use bevy_rapier3d::prelude::*;
 
@@ -7,7 +16,8 @@ pub struct CradlePlugin;
 /// This plugin handles the newtons cradle setup
 impl Plugin for CradlePlugin {
     fn build(&self, app: &mut App) {
-        app.add_systems(OnEnter(GameState::Playing), setup_newtons_cradle);     
+        app.add_systems(OnEnter(GameState::Playing), setup_newtons_cradle)
+            .add_systems(Update, reset_cradle.run_if(in_state(GameState::Playing)));
     }
 }
 
@@ -17,6 +27,7 @@ fn create_rope_joints(
     materials: &mut ResMut<Assets<StandardMaterial>>,
     origin: Vect,
     use_dz: bool,
+    index: usize,
 ) {
     let rad = 1.0;
     let shift = 1.1;
@@ -32,6 +43,8 @@ fn create_rope_joints(
             )),
             RigidBody::Fixed,
             Collider::cuboid(rad, rad, rad),
+            anchor_groups(),
+            CradlePart::default(),
         ))
         .id();
 
@@ -44,6 +57,8 @@ fn create_rope_joints(
             )),
             RigidBody::Fixed,
             Collider::cuboid(rad, rad, rad),
+            anchor_groups(),
+            CradlePart::default(),
         ))
         .id();
 
@@ -83,50 +98,130 @@ fn create_rope_joints(
                 ),
                 ..default()
             },
-            RigidBody::Dynamic,
-            Collider::ball(rad),
-            Friction::default(),
-            Damping::default(), //emulate air resistance
-            ColliderMassProperties::Density(2.0),
-            Restitution {
-                coefficient: 0.9,
-                combine_rule: CoefficientCombineRule::Min,
+            ball_physics(rad),
+            Ball {
+                index,
+                pivot: origin,
             },
-            Velocity::default(),
-            BallSound::default(),
+            CradlePart::default(),
+            SphereMesh { radius: 1.0 },
         ))
-        .insert(ActiveEvents::COLLISION_EVENTS)
         .with_children(|parent| {
             // NOTE: we want to attach multiple impulse joints to this entity, so
             //       we need to add the components to children of the entity. Otherwise
             //       the second joint component would just overwrite the first one.
-            parent.spawn(joint1);
-            parent.spawn(joint2);
+            parent.spawn((joint1, RopeJoint::default()));
+            parent.spawn((joint2, RopeJoint::default()));
         });
 }
 
+/// Everything that makes a ball swing, collide and sound like one of the cradle
+pub fn ball_physics(radius: f32) -> impl Bundle {
+    (
+        RigidBody::Dynamic,
+        Collider::ball(radius),
+        ball_groups(),
+        Friction::default(),
+        Damping::default(), //emulate air resistance
+        ColliderMassProperties::Density(2.0),
+        Restitution {
+            coefficient: 0.9,
+            combine_rule: CoefficientCombineRule::Min,
+        },
+        Velocity::default(),
+        ExternalImpulse::default(),
+        BallSound::default(),
+        Grabbable::default(),
+        ActiveEvents::COLLISION_EVENTS,
+    )
+}
+
 pub fn setup_newtons_cradle(
     mut commands: Commands,
     mut meshes: ResMut<Assets<Mesh>>,
     mut materials: ResMut<Assets<StandardMaterial>>,
+    asset_server: Res<AssetServer>,
+    scene_models: Res<SceneModels>,
+) {
+    match &scene_models.cradle {
+        Some(source) => spawn_cradle_model(&mut commands, &asset_server, source),
+        None => spawn_newtons_cradle(&mut commands, &mut meshes, &mut materials),
+    }
+}
+
+fn reset_cradle(
+    actions: Res<ActionState>,
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    asset_server: Res<AssetServer>,
+    scene_models: Res<SceneModels>,
+    cradle_parts: Query<Entity, With<CradlePart>>,
+    mut history: EventWriter<RecordHistory>,
 ) {
-    let starting_point = 5.0;
-    let offset = 2.01;
+    if actions.just_pressed(Action::Reset) {
+        history.send(RecordHistory);
+        for entity in &cradle_parts {
+            commands.entity(entity).despawn_recursive();
+        }
+        match &scene_models.cradle {
+            Some(source) => spawn_cradle_model(&mut commands, &asset_server, source),
+            None => spawn_newtons_cradle(&mut commands, &mut meshes, &mut materials),
+        }
+    }
+}
+
+const BALL_COUNT: usize = 5;
+const STARTING_POINT: f32 = 5.0;
+const BALL_OFFSET: f32 = 2.01;
+const ANCHOR_HEIGHT: f32 = 10.0;
 
+fn spawn_newtons_cradle(
+    commands: &mut Commands,
+    meshes: &mut ResMut<Assets<Mesh>>,
+    materials: &mut ResMut<Assets<StandardMaterial>>,
+) {
     create_rope_joints(
-        &mut commands,
-        &mut meshes,
-        &mut materials,
-        Vec3::new(starting_point, 10.0, 0.0),
+        commands,
+        meshes,
+        materials,
+        Vec3::new(STARTING_POINT, ANCHOR_HEIGHT, 0.0),
         true,
+        0,
     );
-    for i in 1..5 {
+    for i in 1..BALL_COUNT {
         create_rope_joints(
-            &mut commands,
-            &mut meshes,
-            &mut materials,
-            Vec3::new(i as f32 * offset + starting_point, 10.0, 0.0),
+            commands,
+            meshes,
+            materials,
+            Vec3::new(i as f32 * BALL_OFFSET + STARTING_POINT, ANCHOR_HEIGHT, 0.0),
             false,
+            i,
         );
     }
-}
\ No newline at end of file
+}
+
+/// Middle of the row of pivots, the camera orbits around it
+pub fn cradle_center() -> Vec3 {
+    Vec3::new(
+        STARTING_POINT + (BALL_COUNT - 1) as f32 * BALL_OFFSET / 2.0,
+        ANCHOR_HEIGHT,
+        0.0,
+    )
+}
+
+/// Everything spawned for the cradle, despawned again on reset
+#[derive(Component, Default)]
+pub struct CradlePart {}
+
+/// A rope holding a ball to its anchor, unlike the joint that drags a ball around
+#[derive(Component, Default)]
+pub struct RopeJoint {}
+
+/// A swinging ball of the cradle, numbered from the left end of the row
+#[derive(Component, Clone, Copy, Debug)]
+pub struct Ball {
+    pub index: usize,
+    /// point on the line between the two anchors, the ball swings around it
+    pub pivot: Vec3,
+}
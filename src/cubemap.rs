@@ -0,0 +1,842 @@
+use bevy::{
+    prelude::*,
+    render::render_resource::{
+        Extent3d, TextureDimension, TextureFormat, TextureUsages, TextureViewDescriptor,
+        TextureViewDimension,
+    },
+};
+use std::{f32::consts::PI, fmt};
+
+/// Why an image could not be used as a cubemap
+#[derive(Debug, Clone, PartialEq)]
+pub enum CubemapError {
+    /// the image does not have one of the supported shapes
+    UnsupportedLayout { width: u32, height: u32 },
+    /// the pixels can not be read on the cpu, e.g. block compressed ktx2
+    UnsupportedFormat(TextureFormat),
+}
+
+impl fmt::Display for CubemapError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CubemapError::UnsupportedLayout { width, height } => write!(
+                f,
+                "a {width}x{height} image is not a cubemap, expected a 1x6 vertical strip, \
+                 a 6x1 horizontal strip, a 4x3 horizontal cross \
+                 or a 2x1 equirectangular panorama"
+            ),
+            CubemapError::UnsupportedFormat(format) => {
+                write!(f, "cubemap pixels in {format:?} can not be read")
+            }
+        }
+    }
+}
+
+/// How the six faces are arranged in a single image
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CubemapLayout {
+    /// faces stacked from top to bottom in the order +x, -x, +y, -y, +z, -z
+    VerticalStrip,
+    /// faces side by side in the same order
+    HorizontalStrip,
+    /// +y above, -x +z +x -z in a row and -y below
+    HorizontalCross,
+    /// a panorama, longitude along the width and latitude along the height
+    Equirectangular,
+}
+
+impl CubemapLayout {
+    pub fn detect(width: u32, height: u32) -> Option<CubemapLayout> {
+        if width == 0 || height == 0 {
+            None
+        } else if height == width * 6 {
+            Some(CubemapLayout::VerticalStrip)
+        } else if width == height * 6 {
+            Some(CubemapLayout::HorizontalStrip)
+        } else if width * 3 == height * 4 && width % 4 == 0 {
+            Some(CubemapLayout::HorizontalCross)
+        } else if width == height * 2 {
+            Some(CubemapLayout::Equirectangular)
+        } else {
+            None
+        }
+    }
+
+    /// Column and row of every face, in faces, in the layer order, `None` for a panorama
+    fn face_cells(&self) -> Option<[(u32, u32); 6]> {
+        match self {
+            CubemapLayout::VerticalStrip => Some([(0, 0), (0, 1), (0, 2), (0, 3), (0, 4), (0, 5)]),
+            CubemapLayout::HorizontalStrip => {
+                Some([(0, 0), (1, 0), (2, 0), (3, 0), (4, 0), (5, 0)])
+            }
+            CubemapLayout::HorizontalCross => {
+                Some([(2, 1), (0, 1), (1, 0), (1, 2), (1, 1), (3, 1)])
+            }
+            CubemapLayout::Equirectangular => None,
+        }
+    }
+
+    fn face_size(&self, width: u32, height: u32) -> u32 {
+        match self {
+            CubemapLayout::VerticalStrip => width,
+            CubemapLayout::HorizontalStrip => height,
+            CubemapLayout::HorizontalCross => width / 4,
+            CubemapLayout::Equirectangular => width / 4,
+        }
+    }
+}
+
+/// Turns a loaded image into a cube texture the skybox can sample
+pub fn prepare_skybox(image: &mut Image) -> Result<(), CubemapError> {
+    let size = image.texture_descriptor.size;
+    let unsupported_layout = CubemapError::UnsupportedLayout {
+        width: size.width,
+        height: size.height,
+    };
+
+    if image.texture_descriptor.array_layer_count() == 1 {
+        // NOTE: PNGs and HDRs do not have any metadata that could indicate they contain a cubemap,
+        // so they appear as one texture and the layout is guessed from the aspect ratio.
+        let layout = CubemapLayout::detect(size.width, size.height).ok_or(unsupported_layout)?;
+        match layout {
+            CubemapLayout::VerticalStrip => {}
+            CubemapLayout::Equirectangular => {
+                let face_size = layout.face_size(size.width, size.height) as usize;
+                *image = CubeFaces::from_equirectangular(image, face_size)?.into_image();
+            }
+            _ => restack_faces(image, layout)?,
+        }
+        if image.texture_descriptor.array_layer_count() == 1 {
+            image.reinterpret_stacked_2d_as_array(6);
+        }
+    } else if image.texture_descriptor.array_layer_count() != 6 || size.width != size.height {
+        return Err(unsupported_layout);
+    }
+
+    // full precision floats can not be filtered everywhere, e.g. on webgpu
+    if image.texture_descriptor.format == TextureFormat::Rgba32Float
+        && image.texture_descriptor.mip_level_count == 1
+    {
+        *image = CubeFaces::from_image(image)?.into_image();
+    }
+
+    image.texture_view_descriptor = Some(TextureViewDescriptor {
+        dimension: Some(TextureViewDimension::Cube),
+        ..default()
+    });
+    Ok(())
+}
+
+/// Copies the faces of a strip or cross into a vertical strip, keeping the pixel format
+fn restack_faces(image: &mut Image, layout: CubemapLayout) -> Result<(), CubemapError> {
+    let format = image.texture_descriptor.format;
+    let pixel_bytes = bytes_per_pixel(format).ok_or(CubemapError::UnsupportedFormat(format))?;
+    let width = image.texture_descriptor.size.width as usize;
+    let height = image.texture_descriptor.size.height as usize;
+    let unsupported_layout = CubemapError::UnsupportedLayout {
+        width: width as u32,
+        height: height as u32,
+    };
+    // a panorama has no faces to copy, it is sampled instead
+    let cells = layout
+        .face_cells()
+        .ok_or_else(|| unsupported_layout.clone())?;
+    let face_size = layout.face_size(width as u32, height as u32) as usize;
+    let row_bytes = face_size * pixel_bytes;
+
+    let mut data = Vec::with_capacity(6 * face_size * row_bytes);
+    for (column, row) in cells {
+        for y in 0..face_size {
+            let start = ((row as usize * face_size + y) * width + column as usize * face_size)
+                * pixel_bytes;
+            let Some(bytes) = image.data.get(start..start + row_bytes) else {
+                return Err(unsupported_layout);
+            };
+            data.extend_from_slice(bytes);
+        }
+    }
+
+    image.data = data;
+    image.texture_descriptor.size = Extent3d {
+        width: face_size as u32,
+        height: 6 * face_size as u32,
+        depth_or_array_layers: 1,
+    };
+    Ok(())
+}
+
+/// The six faces of a cubemap in linear rgb, in the layer order +x, -x, +y, -y, +z, -z
+#[derive(Clone)]
+pub struct CubeFaces {
+    pub size: usize,
+    pub faces: Vec<Vec<Vec3>>,
+}
+
+impl CubeFaces {
+    pub fn new(size: usize) -> Self {
+        CubeFaces {
+            size,
+            faces: vec![vec![Vec3::ZERO; size * size]; 6],
+        }
+    }
+
+    /// Reads the largest mip of a six layer image
+    pub fn from_image(image: &Image) -> Result<Self, CubemapError> {
+        let descriptor = &image.texture_descriptor;
+        let size = descriptor.size.width as usize;
+        if descriptor.array_layer_count() != 6 || descriptor.size.height as usize != size {
+            return Err(CubemapError::UnsupportedLayout {
+                width: descriptor.size.width,
+                height: descriptor.size.height,
+            });
+        }
+        let format = descriptor.format;
+        let pixel_bytes = bytes_per_pixel(format).ok_or(CubemapError::UnsupportedFormat(format))?;
+
+        // every layer holds its whole mip chain before the next layer starts
+        let layer_bytes: usize = (0..descriptor.mip_level_count)
+            .map(|mip| (size >> mip).max(1).pow(2) * pixel_bytes)
+            .sum();
+
+        let mut cube = CubeFaces::new(size);
+        for (layer, face) in cube.faces.iter_mut().enumerate() {
+            let start = layer * layer_bytes;
+            let Some(bytes) = image.data.get(start..start + size * size * pixel_bytes) else {
+                return Err(CubemapError::UnsupportedLayout {
+                    width: descriptor.size.width,
+                    height: descriptor.size.height,
+                });
+            };
+            for (texel, pixel) in face.iter_mut().zip(bytes.chunks_exact(pixel_bytes)) {
+                *texel =
+                    decode_pixel(format, pixel).ok_or(CubemapError::UnsupportedFormat(format))?;
+            }
+        }
+        Ok(cube)
+    }
+
+    /// Samples a panorama for every texel of a cube with faces of `size`
+    pub fn from_equirectangular(image: &Image, size: usize) -> Result<Self, CubemapError> {
+        let format = image.texture_descriptor.format;
+        let pixel_bytes = bytes_per_pixel(format).ok_or(CubemapError::UnsupportedFormat(format))?;
+        let width = image.texture_descriptor.size.width as usize;
+        let height = image.texture_descriptor.size.height as usize;
+        let texel = |x: usize, y: usize| {
+            let start = (y * width + x) * pixel_bytes;
+            image
+                .data
+                .get(start..start + pixel_bytes)
+                .and_then(|bytes| decode_pixel(format, bytes))
+                .unwrap_or(Vec3::ZERO)
+        };
+
+        let mut cube = CubeFaces::new(size);
+        for (face, texels) in cube.faces.iter_mut().enumerate() {
+            for y in 0..size {
+                for x in 0..size {
+                    let direction = Self::direction(face, x, y, size);
+                    // -z is the middle of the panorama
+                    let u = 0.5 + direction.x.atan2(-direction.z) / (2.0 * PI);
+                    let v = direction.y.clamp(-1.0, 1.0).acos() / PI;
+                    let px = ((u * width as f32) as usize).min(width - 1);
+                    let py = ((v * height as f32) as usize).min(height - 1);
+                    texels[y * size + x] = texel(px, py);
+                }
+            }
+        }
+        Ok(cube)
+    }
+
+    /// Direction through the middle of a texel
+    pub fn direction(face: usize, x: usize, y: usize, size: usize) -> Vec3 {
+        let u = 2.0 * (x as f32 + 0.5) / size as f32 - 1.0;
+        let v = 2.0 * (y as f32 + 0.5) / size as f32 - 1.0;
+        match face {
+            0 => Vec3::new(1.0, -v, -u),
+            1 => Vec3::new(-1.0, -v, u),
+            2 => Vec3::new(u, 1.0, v),
+            3 => Vec3::new(u, -1.0, -v),
+            4 => Vec3::new(u, -v, 1.0),
+            _ => Vec3::new(-u, -v, -1.0),
+        }
+        .normalize()
+    }
+
+    /// Face and texel coordinates in `0..1` a direction points at
+    pub fn face_coordinates(direction: Vec3) -> (usize, Vec2) {
+        let abs = direction.abs();
+        let (face, u, v, major) = if abs.x >= abs.y && abs.x >= abs.z {
+            if direction.x > 0.0 {
+                (0, -direction.z, -direction.y, abs.x)
+            } else {
+                (1, direction.z, -direction.y, abs.x)
+            }
+        } else if abs.y >= abs.z {
+            if direction.y > 0.0 {
+                (2, direction.x, direction.z, abs.y)
+            } else {
+                (3, direction.x, -direction.z, abs.y)
+            }
+        } else if direction.z > 0.0 {
+            (4, direction.x, -direction.y, abs.z)
+        } else {
+            (5, -direction.x, -direction.y, abs.z)
+        };
+        (face, Vec2::new(u / major, v / major) * 0.5 + 0.5)
+    }
+
+    pub fn sample(&self, direction: Vec3) -> Vec3 {
+        let (face, uv) = Self::face_coordinates(direction);
+        let texel = (uv * self.size as f32 - 0.5).max(Vec2::ZERO);
+        let x0 = (texel.x as usize).min(self.size - 1);
+        let y0 = (texel.y as usize).min(self.size - 1);
+        let x1 = (x0 + 1).min(self.size - 1);
+        let y1 = (y0 + 1).min(self.size - 1);
+        let fx = (texel.x - x0 as f32).clamp(0.0, 1.0);
+        let fy = (texel.y - y0 as f32).clamp(0.0, 1.0);
+
+        let texels = &self.faces[face];
+        let top = texels[y0 * self.size + x0].lerp(texels[y0 * self.size + x1], fx);
+        let bottom = texels[y1 * self.size + x0].lerp(texels[y1 * self.size + x1], fx);
+        top.lerp(bottom, fy)
+    }
+
+    /// Halves the size by averaging every two by two texels
+    pub fn downsample(&self) -> CubeFaces {
+        let size = (self.size / 2).max(1);
+        let mut half = CubeFaces::new(size);
+        for (face, texels) in half.faces.iter_mut().enumerate() {
+            let source = &self.faces[face];
+            for y in 0..size {
+                for x in 0..size {
+                    let mut sum = Vec3::ZERO;
+                    let mut count = 0.0;
+                    for (sx, sy) in [(0, 0), (1, 0), (0, 1), (1, 1)] {
+                        let (sx, sy) = (x * 2 + sx, y * 2 + sy);
+                        if sx < self.size && sy < self.size {
+                            sum += source[sy * self.size + sx];
+                            count += 1.0;
+                        }
+                    }
+                    texels[y * size + x] = sum / count;
+                }
+            }
+        }
+        half
+    }
+
+    /// The cube and every smaller version of it, down to a single texel
+    fn mip_chain(&self) -> Vec<CubeFaces> {
+        let mut chain = vec![self.clone()];
+        while chain.last().is_some_and(|mip| mip.size > 1) {
+            let next = chain.last().map(CubeFaces::downsample);
+            chain.extend(next);
+        }
+        chain
+    }
+
+    /// The cube with faces of exactly `size`, halving as long as that does not go below it
+    /// and sampling the rest of the way, e.g. for faces that are not a power of two
+    fn resized(&self, size: usize) -> CubeFaces {
+        let mut resized = self.clone();
+        while resized.size / 2 >= size {
+            resized = resized.downsample();
+        }
+        if resized.size == size {
+            return resized;
+        }
+        let mut resampled = CubeFaces::new(size);
+        for (face, texels) in resampled.faces.iter_mut().enumerate() {
+            for y in 0..size {
+                for x in 0..size {
+                    texels[y * size + x] = resized.sample(Self::direction(face, x, y, size));
+                }
+            }
+        }
+        resampled
+    }
+
+    /// Cosine weighted light arriving from every direction, for the diffuse environment map
+    pub fn diffuse_irradiance(&self, size: usize) -> CubeFaces {
+        // the integral is smooth, a small copy of the sky is plenty
+        let source = self.resized(16);
+        let mut samples = Vec::with_capacity(6 * source.size * source.size);
+        for (face, texels) in source.faces.iter().enumerate() {
+            for y in 0..source.size {
+                for x in 0..source.size {
+                    let direction = Self::direction(face, x, y, source.size);
+                    samples.push((
+                        direction,
+                        texels[y * source.size + x] * texel_solid_angle(x, y, source.size),
+                    ));
+                }
+            }
+        }
+
+        let mut irradiance = CubeFaces::new(size);
+        for (face, texels) in irradiance.faces.iter_mut().enumerate() {
+            for y in 0..size {
+                for x in 0..size {
+                    let normal = Self::direction(face, x, y, size);
+                    let sum: Vec3 = samples
+                        .iter()
+                        .map(|(direction, radiance)| *radiance * normal.dot(*direction).max(0.0))
+                        .sum();
+                    texels[y * size + x] = sum / PI;
+                }
+            }
+        }
+        irradiance
+    }
+
+    /// Mip chain prefiltered with the GGX lobe, roughness grows from zero at the first
+    /// level to one at the last, the way the pbr shader reads the specular map
+    pub fn specular_mips(&self, size: usize) -> Vec<CubeFaces> {
+        const SAMPLE_COUNT: u32 = 32;
+        let levels = vec![self.resized(size.min(self.size))];
+        // every level halves the first one, as the texture expects
+        let size = levels[0].size;
+        // stop at 4x4, smaller faces only add seams
+        let level_count = (size.max(4).ilog2() - 1) as usize;
+        let source_mips = self.mip_chain();
+        let texel_solid_angle = 4.0 * PI / (6.0 * (self.size * self.size) as f32);
+
+        let mut levels = levels;
+        for level in 1..level_count {
+            let roughness = level as f32 / (level_count - 1) as f32;
+            let alpha = roughness * roughness;
+            let level_size = (size >> level).max(1);
+
+            let mut filtered = CubeFaces::new(level_size);
+            for (face, texels) in filtered.faces.iter_mut().enumerate() {
+                for y in 0..level_size {
+                    for x in 0..level_size {
+                        let normal = Self::direction(face, x, y, level_size);
+                        let (tangent, bitangent) = normal.any_orthonormal_pair();
+
+                        let mut sum = Vec3::ZERO;
+                        let mut weight = 0.0;
+                        for i in 0..SAMPLE_COUNT {
+                            let half = importance_sample_ggx(i, SAMPLE_COUNT, alpha);
+                            let half = tangent * half.x + bitangent * half.y + normal * half.z;
+                            // the view and the normal are the same direction
+                            let light = 2.0 * normal.dot(half) * half - normal;
+                            let n_dot_l = normal.dot(light);
+                            if n_dot_l <= 0.0 {
+                                continue;
+                            }
+
+                            // read from a blurrier mip for less likely samples
+                            let n_dot_h = normal.dot(half).max(0.0);
+                            let pdf = ggx_distribution(n_dot_h, alpha) / 4.0;
+                            let sample_solid_angle = 1.0 / (SAMPLE_COUNT as f32 * pdf + 1e-4);
+                            let mip = (0.5 * (sample_solid_angle / texel_solid_angle).log2() + 1.0)
+                                .clamp(0.0, (source_mips.len() - 1) as f32);
+
+                            sum += source_mips[mip.round() as usize].sample(light) * n_dot_l;
+                            weight += n_dot_l;
+                        }
+                        texels[y * level_size + x] = if weight > 0.0 {
+                            sum / weight
+                        } else {
+                            Vec3::ZERO
+                        };
+                    }
+                }
+            }
+            levels.push(filtered);
+        }
+        levels
+    }
+
+    pub fn into_image(self) -> Image {
+        cube_image(&[self])
+    }
+}
+
+/// A half float cube texture from a mip chain, largest level first
+pub fn cube_image(mips: &[CubeFaces]) -> Image {
+    let size = mips.first().map_or(1, |mip| mip.size) as u32;
+    let mut data = Vec::new();
+    for face in 0..6 {
+        for mip in mips {
+            for texel in &mip.faces[face] {
+                for channel in [texel.x, texel.y, texel.z, 1.0] {
+                    data.extend_from_slice(&f32_to_f16(channel).to_le_bytes());
+                }
+            }
+        }
+    }
+
+    let mut image = Image {
+        data,
+        texture_view_descriptor: Some(TextureViewDescriptor {
+            dimension: Some(TextureViewDimension::Cube),
+            ..default()
+        }),
+        ..default()
+    };
+    image.texture_descriptor.size = Extent3d {
+        width: size,
+        height: size,
+        depth_or_array_layers: 6,
+    };
+    image.texture_descriptor.dimension = TextureDimension::D2;
+    image.texture_descriptor.format = TextureFormat::Rgba16Float;
+    image.texture_descriptor.mip_level_count = mips.len() as u32;
+    image.texture_descriptor.usage = TextureUsages::TEXTURE_BINDING | TextureUsages::COPY_DST;
+    image
+}
+
+/// Share of the sphere one texel covers, texels near the face corners cover less
+fn texel_solid_angle(x: usize, y: usize, size: usize) -> f32 {
+    let u = 2.0 * (x as f32 + 0.5) / size as f32 - 1.0;
+    let v = 2.0 * (y as f32 + 0.5) / size as f32 - 1.0;
+    let texel_area = (2.0 / size as f32).powi(2);
+    texel_area / (1.0 + u * u + v * v).powf(1.5)
+}
+
+/// Half vector around +z for the `index`th of `count` low discrepancy samples
+fn importance_sample_ggx(index: u32, count: u32, alpha: f32) -> Vec3 {
+    let xi = Vec2::new(
+        index as f32 / count as f32,
+        index.reverse_bits() as f32 * 2.328_306_4e-10,
+    );
+    let phi = 2.0 * PI * xi.x;
+    let cos_theta = ((1.0 - xi.y) / (1.0 + (alpha * alpha - 1.0) * xi.y)).sqrt();
+    let sin_theta = (1.0 - cos_theta * cos_theta).max(0.0).sqrt();
+    Vec3::new(phi.cos() * sin_theta, phi.sin() * sin_theta, cos_theta)
+}
+
+fn ggx_distribution(n_dot_h: f32, alpha: f32) -> f32 {
+    let alpha2 = alpha * alpha;
+    let denominator = n_dot_h * n_dot_h * (alpha2 - 1.0) + 1.0;
+    alpha2 / (PI * denominator * denominator).max(1e-6)
+}
+
+fn bytes_per_pixel(format: TextureFormat) -> Option<usize> {
+    match format {
+        TextureFormat::Rgba8Unorm
+        | TextureFormat::Rgba8UnormSrgb
+        | TextureFormat::Bgra8Unorm
+        | TextureFormat::Bgra8UnormSrgb
+        | TextureFormat::Rgb9e5Ufloat => Some(4),
+        TextureFormat::Rgba16Float => Some(8),
+        TextureFormat::Rgba32Float => Some(16),
+        _ => None,
+    }
+}
+
+fn decode_pixel(format: TextureFormat, bytes: &[u8]) -> Option<Vec3> {
+    let unorm = |byte: u8| byte as f32 / 255.0;
+    let half = |index: usize| f16_to_f32(u16::from_le_bytes([bytes[index], bytes[index + 1]]));
+    let float = |index: usize| {
+        f32::from_le_bytes([
+            bytes[index],
+            bytes[index + 1],
+            bytes[index + 2],
+            bytes[index + 3],
+        ])
+    };
+
+    let color = match format {
+        TextureFormat::Rgba8Unorm => Vec3::new(unorm(bytes[0]), unorm(bytes[1]), unorm(bytes[2])),
+        TextureFormat::Rgba8UnormSrgb => Vec3::new(
+            srgb_to_linear(unorm(bytes[0])),
+            srgb_to_linear(unorm(bytes[1])),
+            srgb_to_linear(unorm(bytes[2])),
+        ),
+        TextureFormat::Bgra8Unorm => Vec3::new(unorm(bytes[2]), unorm(bytes[1]), unorm(bytes[0])),
+        TextureFormat::Bgra8UnormSrgb => Vec3::new(
+            srgb_to_linear(unorm(bytes[2])),
+            srgb_to_linear(unorm(bytes[1])),
+            srgb_to_linear(unorm(bytes[0])),
+        ),
+        TextureFormat::Rgb9e5Ufloat => {
+            let bits = u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]);
+            let scale = 2f32.powi((bits >> 27) as i32 - 15 - 9);
+            Vec3::new(
+                (bits & 0x1ff) as f32,
+                ((bits >> 9) & 0x1ff) as f32,
+                ((bits >> 18) & 0x1ff) as f32,
+            ) * scale
+        }
+        TextureFormat::Rgba16Float => Vec3::new(half(0), half(2), half(4)),
+        TextureFormat::Rgba32Float => Vec3::new(float(0), float(4), float(8)),
+        _ => return None,
+    };
+    Some(color)
+}
+
+fn srgb_to_linear(value: f32) -> f32 {
+    if value <= 0.04045 {
+        value / 12.92
+    } else {
+        ((value + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+/// Colors are never negative, so the sign is dropped
+fn f32_to_f16(value: f32) -> u16 {
+    const MAX_HALF: u16 = 0x7bff;
+    if value.is_nan() || value <= 0.0 {
+        return 0;
+    }
+    let bits = value.to_bits();
+    let exponent = ((bits >> 23) & 0xff) as i32 - 127 + 15;
+    if exponent <= 0 {
+        return 0;
+    }
+    if exponent >= 31 {
+        return MAX_HALF;
+    }
+    let mut half = ((exponent as u32) << 10) | ((bits >> 13) & 0x3ff);
+    // round to nearest, a carry into the exponent is still the right number
+    if bits & 0x1000 != 0 {
+        half += 1;
+    }
+    half.min(MAX_HALF as u32) as u16
+}
+
+fn f16_to_f32(half: u16) -> f32 {
+    let exponent = ((half >> 10) & 0x1f) as u32;
+    let mantissa = (half & 0x3ff) as u32;
+    let magnitude = match exponent {
+        0 => mantissa as f32 * 2f32.powi(-24),
+        31 => 65504.0,
+        _ => f32::from_bits(((exponent + 112) << 23) | (mantissa << 13)),
+    };
+    if half & 0x8000 != 0 {
+        -magnitude
+    } else {
+        magnitude
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn uniform(size: usize, color: Vec3) -> CubeFaces {
+        CubeFaces {
+            size,
+            faces: vec![vec![color; size * size]; 6],
+        }
+    }
+
+    #[test]
+    fn texel_solid_angles_cover_the_sphere() {
+        let size = 16;
+        let total: f32 = (0..size)
+            .flat_map(|y| (0..size).map(move |x| texel_solid_angle(x, y, size)))
+            .sum::<f32>()
+            * 6.0;
+        assert!((total - 4.0 * PI).abs() < 0.01, "{total}");
+    }
+
+    #[test]
+    fn resized_faces_have_the_asked_size() {
+        let cube = uniform(12, Vec3::ONE);
+        assert_eq!(cube.resized(6).size, 6);
+        assert_eq!(cube.resized(8).size, 8);
+        assert_eq!(cube.resized(5).size, 5);
+        assert_eq!(cube.resized(12).size, 12);
+    }
+
+    #[test]
+    fn specular_mips_halve_from_the_asked_size() {
+        let color = Vec3::new(0.2, 0.5, 1.0);
+        // not a power of two, the first level used to stay at the halved 6
+        let levels = uniform(12, color).specular_mips(8);
+        let sizes: Vec<usize> = levels.iter().map(|level| level.size).collect();
+        assert_eq!(sizes, [8, 4]);
+        for level in &levels {
+            for texel in level.faces.iter().flatten() {
+                assert!(texel.abs_diff_eq(color, 1e-3), "{texel}");
+            }
+        }
+
+        let image = cube_image(&levels);
+        assert_eq!(image.texture_descriptor.mip_level_count, 2);
+        assert_eq!(image.data.len(), 6 * (8 * 8 + 4 * 4) * 8);
+    }
+
+    #[test]
+    fn irradiance_of_a_uniform_sky_is_the_sky() {
+        let color = Vec3::new(1.0, 0.5, 0.25);
+        let irradiance = uniform(8, color).diffuse_irradiance(4);
+        for texel in irradiance.faces.iter().flatten() {
+            assert!(texel.abs_diff_eq(color, 0.02), "{texel}");
+        }
+    }
+
+    #[test]
+    fn face_coordinates_invert_direction() {
+        let size = 4;
+        for face in 0..6 {
+            for y in 0..size {
+                for x in 0..size {
+                    let (found, uv) =
+                        CubeFaces::face_coordinates(CubeFaces::direction(face, x, y, size));
+                    assert_eq!(found, face);
+                    let texel = uv * size as f32 - 0.5;
+                    assert!(
+                        texel.abs_diff_eq(Vec2::new(x as f32, y as f32), 1e-4),
+                        "{texel}"
+                    );
+                }
+            }
+        }
+    }
+
+    /// One distinct color per face, in the layer order
+    const FACE_COLORS: [[u8; 4]; 6] = [
+        [255, 0, 0, 255],
+        [0, 255, 0, 255],
+        [0, 0, 255, 255],
+        [255, 255, 0, 255],
+        [0, 255, 255, 255],
+        [255, 0, 255, 255],
+    ];
+
+    /// An image `columns` by `rows` faces of `face_size`, painted with the face at each cell
+    fn layout_image(columns: u32, rows: u32, face_size: u32, cells: [(u32, u32); 6]) -> Image {
+        let (width, height) = (columns * face_size, rows * face_size);
+        let mut data = vec![0; (width * height * 4) as usize];
+        for (color, (column, row)) in FACE_COLORS.iter().zip(cells) {
+            for y in row * face_size..(row + 1) * face_size {
+                for x in column * face_size..(column + 1) * face_size {
+                    let start = ((y * width + x) * 4) as usize;
+                    data[start..start + 4].copy_from_slice(color);
+                }
+            }
+        }
+        Image::new(
+            Extent3d {
+                width,
+                height,
+                depth_or_array_layers: 1,
+            },
+            TextureDimension::D2,
+            data,
+            TextureFormat::Rgba8Unorm,
+        )
+    }
+
+    fn assert_faces_in_order(mut image: Image) {
+        prepare_skybox(&mut image).unwrap();
+        assert_eq!(image.texture_descriptor.array_layer_count(), 6);
+        let cube = CubeFaces::from_image(&image).unwrap();
+        for (face, color) in cube.faces.iter().zip(FACE_COLORS) {
+            let expected = Vec3::new(color[0] as f32, color[1] as f32, color[2] as f32) / 255.0;
+            assert!(face.iter().all(|texel| *texel == expected), "{expected}");
+        }
+    }
+
+    #[test]
+    fn vertical_strip_faces_stay_in_order() {
+        let cells = [(0, 0), (0, 1), (0, 2), (0, 3), (0, 4), (0, 5)];
+        assert_faces_in_order(layout_image(1, 6, 2, cells));
+    }
+
+    #[test]
+    fn horizontal_strip_faces_are_restacked_in_order() {
+        let cells = [(0, 0), (1, 0), (2, 0), (3, 0), (4, 0), (5, 0)];
+        assert_faces_in_order(layout_image(6, 1, 2, cells));
+    }
+
+    #[test]
+    fn horizontal_cross_faces_are_restacked_in_order() {
+        // +x right of +z, -x left of it, +y above, -y below and -z at the end of the row
+        let cells = [(2, 1), (0, 1), (1, 0), (1, 2), (1, 1), (3, 1)];
+        assert_faces_in_order(layout_image(4, 3, 2, cells));
+    }
+
+    #[test]
+    fn equirectangular_sky_ends_up_on_the_right_faces() {
+        let (width, height) = (16, 8);
+        let mut data = Vec::new();
+        for y in 0..height {
+            for x in 0..width {
+                let color = match y {
+                    0..=1 => [0, 0, 255, 255],
+                    6..=7 => [255, 0, 0, 255],
+                    // the middle of the panorama looks along -z
+                    _ if (6..10).contains(&x) => [0, 255, 0, 255],
+                    _ => [0, 0, 0, 255],
+                };
+                data.extend_from_slice(&color);
+            }
+        }
+        let mut image = Image::new(
+            Extent3d {
+                width,
+                height,
+                depth_or_array_layers: 1,
+            },
+            TextureDimension::D2,
+            data,
+            TextureFormat::Rgba8Unorm,
+        );
+        prepare_skybox(&mut image).unwrap();
+        let cube = CubeFaces::from_image(&image).unwrap();
+        assert_eq!(cube.size, 4);
+
+        let texel = |face: usize| cube.faces[face][cube.size + 1];
+        assert_eq!(texel(2), Vec3::Z);
+        assert_eq!(texel(3), Vec3::X);
+        assert_eq!(texel(5), Vec3::Y);
+        assert_eq!(texel(4), Vec3::ZERO);
+    }
+
+    #[test]
+    fn a_panorama_is_not_restacked() {
+        let mut image = layout_image(2, 1, 2, [(0, 0); 6]);
+        assert_eq!(
+            restack_faces(&mut image, CubemapLayout::Equirectangular),
+            Err(CubemapError::UnsupportedLayout {
+                width: 4,
+                height: 2
+            })
+        );
+    }
+
+    #[test]
+    fn other_shapes_are_rejected() {
+        for (width, height) in [(10, 10), (30, 20), (10, 7), (0, 0), (6, 4)] {
+            let mut image = Image::new_fill(
+                Extent3d {
+                    width: width.max(1),
+                    height: height.max(1),
+                    depth_or_array_layers: 1,
+                },
+                TextureDimension::D2,
+                &[0, 0, 0, 255],
+                TextureFormat::Rgba8Unorm,
+            );
+            image.texture_descriptor.size.width = width;
+            image.texture_descriptor.size.height = height;
+            assert_eq!(
+                prepare_skybox(&mut image),
+                Err(CubemapError::UnsupportedLayout { width, height })
+            );
+        }
+    }
+
+    #[test]
+    fn half_floats_round_trip() {
+        assert_eq!(f32_to_f16(1.0), 0x3c00);
+        assert_eq!(f32_to_f16(0.5), 0x3800);
+        for value in [0.001, 0.1, 0.5, 1.0, 3.75, 100.0, 1000.5, 65000.0] {
+            let back = f16_to_f32(f32_to_f16(value));
+            assert!(
+                (back - value).abs() <= value * 1e-3,
+                "{value} came back as {back}"
+            );
+        }
+        // colors are never negative and too bright is the brightest half
+        assert_eq!(f16_to_f32(f32_to_f16(-2.0)), 0.0);
+        assert_eq!(f16_to_f32(f32_to_f16(f32::NAN)), 0.0);
+        assert_eq!(f16_to_f32(f32_to_f16(1e9)), 65504.0);
+    }
+}
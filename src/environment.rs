@@ -0,0 +1,320 @@
+use crate::{
+    actions::{Action, ActionState},
+    camera::MainCamera,
+    config::load_config,
+    cubemap::{cube_image, prepare_skybox, CubeFaces, CubemapError},
+    replay::ReplayCamera,
+    GameState,
+};
+use bevy::{
+    asset::LoadState,
+    core_pipeline::Skybox,
+    prelude::*,
+    tasks::{block_on, AsyncComputeTaskPool, Task},
+    utils::HashMap,
+};
+use serde::{Deserialize, Serialize};
+
+pub struct EnvironmentPlugin;
+
+const ENVIRONMENT_CONFIG: &str = "environments";
+
+/// This plugin puts the sky around the cradle and lights the balls with it,
+/// the environment can be switched while the game runs
+impl Plugin for EnvironmentPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(load_config::<Environments>(ENVIRONMENT_CONFIG).unwrap_or_default())
+            .insert_resource(CurrentEnvironment::default())
+            .insert_resource(LoadedEnvironments::default())
+            .add_systems(
+                Update,
+                (
+                    cycle_environment.run_if(in_state(GameState::Playing)),
+                    load_environment,
+                    prepare_environment,
+                    apply_environment,
+                )
+                    .chain()
+                    .run_if(not(in_state(GameState::Loading))),
+            );
+    }
+}
+
+/// Where the images of one environment are found, relative to the assets folder
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct EnvironmentSource {
+    pub name: String,
+    /// the cubemap shown as the sky, a png or hdr strip or a ktx2 cube
+    pub skybox: String,
+    /// irradiance cube prefiltered offline, e.g. with glTF-IBL-Sampler,
+    /// computed from the skybox when missing
+    pub diffuse: Option<String>,
+    /// GGX prefiltered cube with one roughness per mip, computed from the skybox when missing
+    pub specular: Option<String>,
+}
+
+/// The environments to choose from, more can be listed in `config/environments.ron`
+#[derive(Resource, Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Environments {
+    pub list: Vec<EnvironmentSource>,
+}
+
+impl Default for Environments {
+    fn default() -> Self {
+        Environments {
+            list: vec![
+                EnvironmentSource {
+                    name: "Vondelpark".to_string(),
+                    skybox: "environment_maps/sunny_vondelpark.png".to_string(),
+                    diffuse: None,
+                    specular: None,
+                },
+                // a panorama with a sun far brighter than white, lights the balls like a real sky
+                EnvironmentSource {
+                    name: "Dusk".to_string(),
+                    skybox: "environment_maps/procedural_dusk.hdr".to_string(),
+                    diffuse: None,
+                    specular: None,
+                },
+                // a half float cube with bright softboxes, shows off the reflections
+                EnvironmentSource {
+                    name: "Studio".to_string(),
+                    skybox: "environment_maps/procedural_studio.ktx2".to_string(),
+                    diffuse: None,
+                    specular: None,
+                },
+            ],
+        }
+    }
+}
+
+/// Index into [`Environments::list`] of the environment shown
+#[derive(Resource, Default)]
+pub struct CurrentEnvironment {
+    pub index: usize,
+}
+
+/// Face size of the computed irradiance cube
+const DIFFUSE_SIZE: usize = 32;
+/// Face size of the largest mip of the computed specular cube
+const SPECULAR_SIZE: usize = 128;
+
+#[derive(Clone)]
+struct EnvironmentMaps {
+    skybox: Handle<Image>,
+    diffuse: Handle<Image>,
+    specular: Handle<Image>,
+}
+
+/// Lighting maps computed from the skybox in the background, the missing ones are `None`
+struct ComputingMaps {
+    skybox: Handle<Image>,
+    diffuse: Option<Handle<Image>>,
+    specular: Option<Handle<Image>>,
+    task: Task<Result<(Option<Image>, Option<Image>), CubemapError>>,
+}
+
+/// Environments being loaded, being computed and ready to show, by index
+#[derive(Resource, Default)]
+struct LoadedEnvironments {
+    loading: HashMap<usize, (Handle<Image>, Option<Handle<Image>>, Option<Handle<Image>>)>,
+    computing: HashMap<usize, ComputingMaps>,
+    ready: HashMap<usize, EnvironmentMaps>,
+}
+
+fn cycle_environment(
+    actions: Res<ActionState>,
+    environments: Res<Environments>,
+    mut current: ResMut<CurrentEnvironment>,
+) {
+    if actions.just_pressed(Action::NextEnvironment) && !environments.list.is_empty() {
+        current.index = (current.index + 1) % environments.list.len();
+    }
+}
+
+fn load_environment(
+    asset_server: Res<AssetServer>,
+    environments: Res<Environments>,
+    current: Res<CurrentEnvironment>,
+    mut loaded: ResMut<LoadedEnvironments>,
+) {
+    if !current.is_changed() {
+        return;
+    }
+    let index = current.index;
+    if loaded.ready.contains_key(&index)
+        || loaded.loading.contains_key(&index)
+        || loaded.computing.contains_key(&index)
+    {
+        return;
+    }
+    let Some(source) = environments.list.get(index) else {
+        error!("there is no environment {index}");
+        return;
+    };
+    info!("loading environment {}", source.name);
+
+    let handles = (
+        asset_server.load(source.skybox.as_str()),
+        source
+            .diffuse
+            .as_ref()
+            .map(|path| asset_server.load(path.as_str())),
+        source
+            .specular
+            .as_ref()
+            .map(|path| asset_server.load(path.as_str())),
+    );
+    loaded.loading.insert(index, handles);
+}
+
+/// Once the images are in, turns them into cubes and computes the missing lighting maps
+/// on the async compute pool
+fn prepare_environment(
+    asset_server: Res<AssetServer>,
+    environments: Res<Environments>,
+    mut images: ResMut<Assets<Image>>,
+    mut loaded: ResMut<LoadedEnvironments>,
+) {
+    let loaded = &mut *loaded;
+    loaded.loading.retain(|index, (skybox, diffuse, specular)| {
+        let name = environments
+            .list
+            .get(*index)
+            .map_or("?", |source| source.name.as_str());
+        let handles = [Some(&*skybox), diffuse.as_ref(), specular.as_ref()];
+        let handles = handles.into_iter().flatten();
+        if handles
+            .clone()
+            .any(|handle| asset_server.get_load_state(handle) == LoadState::Failed)
+        {
+            error!("environment {name} failed to load");
+            return false;
+        }
+        if handles.clone().any(|handle| images.get(handle).is_none()) {
+            return true;
+        }
+
+        for handle in handles {
+            let Some(image) = images.get_mut(handle) else {
+                continue;
+            };
+            if let Err(err) = prepare_skybox(image) {
+                let path = asset_server
+                    .get_handle_path(handle)
+                    .map(|path| path.path().display().to_string())
+                    .unwrap_or_default();
+                error!("environment {name}: {path}: {err}");
+                return false;
+            }
+        }
+
+        match (diffuse.clone(), specular.clone()) {
+            (Some(diffuse), Some(specular)) => {
+                loaded.ready.insert(
+                    *index,
+                    EnvironmentMaps {
+                        skybox: skybox.clone(),
+                        diffuse,
+                        specular,
+                    },
+                );
+            }
+            (diffuse, specular) => {
+                // filtering takes seconds, the game keeps running meanwhile
+                let sky = images
+                    .get(skybox)
+                    .expect("the skybox was checked above")
+                    .clone();
+                let (compute_diffuse, compute_specular) = (diffuse.is_none(), specular.is_none());
+                let task = AsyncComputeTaskPool::get().spawn(async move {
+                    let sky = CubeFaces::from_image(&sky)?;
+                    Ok((
+                        compute_diffuse.then(|| sky.diffuse_irradiance(DIFFUSE_SIZE).into_image()),
+                        compute_specular.then(|| cube_image(&sky.specular_mips(SPECULAR_SIZE))),
+                    ))
+                });
+                loaded.computing.insert(
+                    *index,
+                    ComputingMaps {
+                        skybox: skybox.clone(),
+                        diffuse,
+                        specular,
+                        task,
+                    },
+                );
+            }
+        }
+        false
+    });
+
+    let finished: Vec<usize> = loaded
+        .computing
+        .iter()
+        .filter(|(_, computing)| computing.task.is_finished())
+        .map(|(index, _)| *index)
+        .collect();
+    for index in finished {
+        let Some(computing) = loaded.computing.remove(&index) else {
+            continue;
+        };
+        let (diffuse, specular) = block_on(computing.task).unwrap_or_else(|err| {
+            let name = environments
+                .list
+                .get(index)
+                .map_or("?", |source| source.name.as_str());
+            // still lit, only without the blur rough surfaces need
+            warn!("environment {name}: lighting maps can not be computed, {err}");
+            (None, None)
+        });
+        let mut computed = |given: Option<Handle<Image>>, image: Option<Image>| {
+            given
+                .or_else(|| image.map(|image| images.add(image)))
+                .unwrap_or_else(|| computing.skybox.clone())
+        };
+        let diffuse = computed(computing.diffuse, diffuse);
+        let specular = computed(computing.specular, specular);
+        loaded.ready.insert(
+            index,
+            EnvironmentMaps {
+                skybox: computing.skybox,
+                diffuse,
+                specular,
+            },
+        );
+    }
+}
+
+fn apply_environment(
+    mut commands: Commands,
+    current: Res<CurrentEnvironment>,
+    loaded: Res<LoadedEnvironments>,
+    cameras: Query<(Entity, Option<&Skybox>), With<MainCamera>>,
+    replay_cameras: Query<(Entity, Option<&EnvironmentMapLight>), With<ReplayCamera>>,
+) {
+    let Some(maps) = loaded.ready.get(&current.index) else {
+        return;
+    };
+    // the inset keeps its plain background, only the balls are lit the same
+    for (entity, light) in &replay_cameras {
+        if light.is_some_and(|light| light.specular_map == maps.specular) {
+            continue;
+        }
+        commands.entity(entity).insert(EnvironmentMapLight {
+            diffuse_map: maps.diffuse.clone(),
+            specular_map: maps.specular.clone(),
+        });
+    }
+    for (entity, skybox) in &cameras {
+        if skybox.is_some_and(|skybox| skybox.0 == maps.skybox) {
+            continue;
+        }
+        commands.entity(entity).insert((
+            Skybox(maps.skybox.clone()),
+            EnvironmentMapLight {
+                diffuse_map: maps.diffuse.clone(),
+                specular_map: maps.specular.clone(),
+            },
+        ));
+    }
+}
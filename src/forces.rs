@@ -0,0 +1,124 @@
+use crate::{
+    actions::{Action, ActionState},
+    arrows::draw_arrow,
+    cradle::{Ball, RopeJoint},
+    GameState,
+};
+use bevy::prelude::*;
+use bevy_rapier3d::prelude::*;
+
+pub struct ForcesPlugin;
+
+/// This plugin draws the velocity of every ball and the forces on it as arrows:
+/// gravity and the pull of each rope, read from the impulses of the rope joints
+impl Plugin for ForcesPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(ForceSettings::default()).add_systems(
+            Update,
+            (
+                toggle_forces.run_if(in_state(GameState::Playing)),
+                draw_forces.run_if(in_state(GameState::Playing)),
+            )
+                .chain(),
+        );
+    }
+}
+
+#[derive(Resource, Clone, Debug, PartialEq)]
+pub struct ForceSettings {
+    pub visible: bool,
+    /// arrow length per unit of speed
+    pub velocity_scale: f32,
+    /// arrow length per unit of force, the same for gravity and the ropes
+    pub force_scale: f32,
+}
+
+impl Default for ForceSettings {
+    fn default() -> Self {
+        ForceSettings {
+            visible: false,
+            velocity_scale: 0.3,
+            force_scale: 0.01,
+        }
+    }
+}
+
+const VELOCITY_COLOR: Color = Color::rgb(0.2, 0.9, 1.0);
+const GRAVITY_COLOR: Color = Color::rgb(1.0, 0.85, 0.2);
+const TENSION_COLOR: Color = Color::rgb(1.0, 0.3, 0.8);
+
+fn toggle_forces(actions: Res<ActionState>, mut force_settings: ResMut<ForceSettings>) {
+    if actions.just_pressed(Action::ToggleForces) {
+        force_settings.visible = !force_settings.visible;
+    }
+}
+
+fn draw_forces(
+    force_settings: Res<ForceSettings>,
+    rapier_context: Res<RapierContext>,
+    rapier_config: Res<RapierConfiguration>,
+    balls: Query<(Entity, &GlobalTransform, &Velocity), With<Ball>>,
+    ropes: Query<(&ImpulseJoint, &RapierImpulseJointHandle, &Parent), With<RopeJoint>>,
+    anchors: Query<&GlobalTransform>,
+    mut gizmos: Gizmos,
+) {
+    if !force_settings.visible {
+        return;
+    }
+
+    for (entity, transform, velocity) in &balls {
+        let center = transform.translation();
+        draw_arrow(
+            &mut gizmos,
+            center,
+            center + velocity.linvel * force_settings.velocity_scale,
+            VELOCITY_COLOR,
+        );
+
+        let mass = rapier_context
+            .entity2body()
+            .get(&entity)
+            .and_then(|handle| rapier_context.bodies.get(*handle))
+            .map_or(0.0, |body| body.mass());
+        draw_arrow(
+            &mut gizmos,
+            center,
+            center + rapier_config.gravity * mass * force_settings.force_scale,
+            GRAVITY_COLOR,
+        );
+    }
+
+    // impulses are what the solver applied over the last step, a force needs the step length
+    let dt = rapier_context.integration_parameters.dt;
+    if dt <= 0.0 {
+        return;
+    }
+    for (joint, handle, parent) in &ropes {
+        let Ok((_, ball_transform, _)) = balls.get(parent.get()) else {
+            continue;
+        };
+        let Some(rapier_joint) = rapier_context.impulse_joints.get(handle.0) else {
+            continue;
+        };
+        let Ok(anchor_transform) = anchors.get(joint.parent) else {
+            continue;
+        };
+
+        // the rope only pulls, along itself from the ball to the anchor
+        let tension = rapier_joint.impulses.xyz().norm() / dt;
+        // rapier anchors are already in world units, only move and turn them
+        let on_body = |transform: &GlobalTransform, anchor: Vec3| {
+            let (_, rotation, translation) = transform.to_scale_rotation_translation();
+            translation + rotation * anchor
+        };
+        let start = on_body(ball_transform, joint.data.local_anchor2());
+        let end = on_body(anchor_transform, joint.data.local_anchor1());
+        let direction = (end - start).normalize_or_zero();
+        draw_arrow(
+            &mut gizmos,
+            start,
+            start + direction * tension * force_settings.force_scale,
+            TENSION_COLOR,
+        );
+    }
+}
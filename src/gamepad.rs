@@ -0,0 +1,194 @@
+use crate::{
+    actions::{Action, ActionState},
+    camera::OrbitCamera,
+    cradle::Ball,
+    history::RecordHistory,
+    lift::{lift_ball, lift_direction, release_ball, Lifted},
+    GameState,
+};
+use bevy::prelude::*;
+
+pub struct GamepadControlPlugin;
+
+/// This plugin lets a controller pick a ball, pull it back with the analog trigger
+/// and orbit the camera with the sticks, so the cradle can run as a kiosk exhibit
+impl Plugin for GamepadControlPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(GamepadControl::default()).add_systems(
+            Update,
+            (
+                cycle_selected_ball.run_if(in_state(GameState::Playing)),
+                mark_selected_ball.run_if(in_state(GameState::Playing)),
+                lift_with_trigger.run_if(in_state(GameState::Playing)),
+                orbit_with_sticks.run_if(in_state(GameState::Playing)),
+            )
+                .chain(),
+        );
+    }
+}
+
+#[derive(Resource)]
+pub struct GamepadControl {
+    pub selected_ball: usize,
+    /// lift angle at a fully pressed trigger, in radians
+    pub max_lift_angle: f32,
+    /// trigger values below this let go of the ball
+    pub trigger_threshold: f32,
+    /// radians per second at full stick
+    pub orbit_speed: f32,
+    /// zoom factor per second at full stick
+    pub zoom_speed: f32,
+    /// the ball currently pulled back by the trigger
+    holding: Option<Entity>,
+}
+
+impl Default for GamepadControl {
+    fn default() -> Self {
+        GamepadControl {
+            selected_ball: 0,
+            max_lift_angle: 70f32.to_radians(),
+            trigger_threshold: 0.1,
+            orbit_speed: 1.5,
+            zoom_speed: 1.0,
+            holding: None,
+        }
+    }
+}
+
+/// The ball the gamepad is pointing at
+#[derive(Component, Default)]
+pub struct GamepadSelected {}
+
+fn cycle_selected_ball(
+    actions: Res<ActionState>,
+    mut gamepad_control: ResMut<GamepadControl>,
+    balls: Query<&Ball>,
+) {
+    let ball_count = balls.iter().count();
+    if ball_count == 0 {
+        return;
+    }
+
+    if actions.just_pressed(Action::SelectNextBall) {
+        gamepad_control.selected_ball = (gamepad_control.selected_ball + 1) % ball_count;
+    }
+    if actions.just_pressed(Action::SelectPreviousBall) {
+        gamepad_control.selected_ball =
+            (gamepad_control.selected_ball + ball_count - 1) % ball_count;
+    }
+}
+
+fn mark_selected_ball(
+    mut commands: Commands,
+    gamepads: Res<Gamepads>,
+    gamepad_control: Res<GamepadControl>,
+    balls: Query<(Entity, &Ball, Option<&GamepadSelected>)>,
+) {
+    // only show the selection while a controller is plugged in
+    let has_gamepad = gamepads.iter().next().is_some();
+    for (entity, ball, selected) in &balls {
+        let should_select = has_gamepad && ball.index == gamepad_control.selected_ball;
+        match (should_select, selected.is_some()) {
+            (true, false) => {
+                commands.entity(entity).insert(GamepadSelected::default());
+            }
+            (false, true) => {
+                commands.entity(entity).remove::<GamepadSelected>();
+            }
+            _ => {}
+        }
+    }
+}
+
+fn lift_with_trigger(
+    mut commands: Commands,
+    gamepads: Res<Gamepads>,
+    button_axes: Res<Axis<GamepadButton>>,
+    mut gamepad_control: ResMut<GamepadControl>,
+    mut balls: Query<(Entity, &Ball, Option<&mut Lifted>)>,
+    mut history: EventWriter<RecordHistory>,
+) {
+    let trigger = gamepads
+        .iter()
+        .filter_map(|gamepad| {
+            button_axes.get(GamepadButton::new(
+                gamepad,
+                GamepadButtonType::RightTrigger2,
+            ))
+        })
+        .fold(0.0, f32::max);
+
+    if trigger < gamepad_control.trigger_threshold {
+        // letting go of the trigger drops the ball from where it is
+        if let Some(entity) = gamepad_control.holding.take() {
+            if balls
+                .get(entity)
+                .is_ok_and(|(_, _, lifted)| lifted.is_some())
+            {
+                release_ball(&mut commands, entity);
+            }
+        }
+        return;
+    }
+
+    let ball_count = balls.iter().count();
+    for (entity, ball, lifted) in &mut balls {
+        if ball.index != gamepad_control.selected_ball {
+            continue;
+        }
+        let angle =
+            lift_direction(ball.index, ball_count) * trigger * gamepad_control.max_lift_angle;
+
+        match (gamepad_control.holding, lifted) {
+            (None, _) => {
+                history.send(RecordHistory);
+                lift_ball(&mut commands, entity, angle);
+                gamepad_control.holding = Some(entity);
+            }
+            // easing off the trigger does not lower the ball, it only ever goes higher
+            (Some(holding), Some(mut lifted)) if holding == entity => {
+                if angle.abs() > lifted.angle.abs() {
+                    lifted.angle = angle;
+                }
+            }
+            // released with a button while the trigger is still pressed,
+            // wait for the trigger to come back before lifting again
+            _ => {}
+        }
+    }
+}
+
+fn orbit_with_sticks(
+    time: Res<Time>,
+    actions: Res<ActionState>,
+    gamepads: Res<Gamepads>,
+    axes: Res<Axis<GamepadAxis>>,
+    gamepad_control: Res<GamepadControl>,
+    mut cameras: Query<&mut OrbitCamera>,
+) {
+    // the camera stays put behind the settings panel
+    if actions.suspend_gameplay {
+        return;
+    }
+    for gamepad in gamepads.iter() {
+        let axis = |axis_type| {
+            axes.get(GamepadAxis::new(gamepad, axis_type))
+                .unwrap_or(0.0)
+        };
+        let orbit = Vec2::new(
+            axis(GamepadAxisType::LeftStickX),
+            axis(GamepadAxisType::LeftStickY),
+        ) * gamepad_control.orbit_speed
+            * time.delta_seconds();
+        let zoom =
+            axis(GamepadAxisType::RightStickY) * gamepad_control.zoom_speed * time.delta_seconds();
+
+        if orbit == Vec2::ZERO && zoom == 0.0 {
+            continue;
+        }
+        for mut camera in &mut cameras {
+            camera.orbit(-orbit.x, -orbit.y);
+            camera.zoom(1.0 - zoom);
+        }
+    }
+}
@@ -0,0 +1,326 @@
+use crate::{
+    camera::MainCamera,
+    config::{load_config, save_config},
+};
+use bevy::{
+    core_pipeline::tonemapping::Tonemapping,
+    pbr::{CascadeShadowConfig, CascadeShadowConfigBuilder},
+    prelude::*,
+    utils::HashMap,
+};
+use serde::{Deserialize, Serialize};
+
+pub struct GraphicsPlugin;
+
+const GRAPHICS_CONFIG: &str = "graphics";
+
+/// This plugin applies the graphics quality settings while the game runs and remembers them
+impl Plugin for GraphicsPlugin {
+    fn build(&self, app: &mut App) {
+        let mut graphics_settings =
+            load_config::<GraphicsSettings>(GRAPHICS_CONFIG).unwrap_or_default();
+        // a config saved by the desktop build can ask for more than the browser does
+        graphics_settings.msaa = graphics_settings.msaa.supported();
+        app.insert_resource(graphics_settings.msaa.into_msaa())
+            .insert_resource(graphics_settings)
+            .add_systems(
+                Update,
+                (
+                    apply_render_settings,
+                    apply_light_settings,
+                    apply_camera_settings,
+                    apply_sphere_detail,
+                    save_graphics_settings,
+                ),
+            );
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum GraphicsPreset {
+    /// the web build and integrated graphics
+    Low,
+    Medium,
+    High,
+    /// any setting changed by hand
+    Custom,
+}
+
+impl GraphicsPreset {
+    const CHOICES: [GraphicsPreset; 3] = [
+        GraphicsPreset::Low,
+        GraphicsPreset::Medium,
+        GraphicsPreset::High,
+    ];
+
+    pub fn next(&self, direction: i32) -> GraphicsPreset {
+        let current = Self::CHOICES
+            .iter()
+            .position(|preset| preset == self)
+            .unwrap_or(Self::CHOICES.len() - 1) as i32;
+        Self::CHOICES[(current + direction).rem_euclid(Self::CHOICES.len() as i32) as usize]
+    }
+
+    pub fn settings(&self) -> GraphicsSettings {
+        match self {
+            GraphicsPreset::Low => GraphicsSettings {
+                preset: *self,
+                msaa: MsaaSamples::Off,
+                shadows: true,
+                shadow_map_size: 1024,
+                shadow_cascades: 1,
+                fog: false,
+                tonemapping: TonemappingMode::Reinhard,
+                sphere_detail: 12,
+            },
+            GraphicsPreset::Medium => GraphicsSettings {
+                preset: *self,
+                msaa: MsaaSamples::Sample4,
+                shadows: true,
+                shadow_map_size: 2048,
+                shadow_cascades: 2,
+                fog: true,
+                tonemapping: TonemappingMode::TonyMcMapface,
+                sphere_detail: 24,
+            },
+            GraphicsPreset::High | GraphicsPreset::Custom => GraphicsSettings {
+                preset: GraphicsPreset::High,
+                msaa: MsaaSamples::Sample8.supported(),
+                shadows: true,
+                shadow_map_size: 4096,
+                shadow_cascades: 4,
+                fog: true,
+                tonemapping: TonemappingMode::TonyMcMapface,
+                sphere_detail: 36,
+            },
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum MsaaSamples {
+    Off,
+    Sample2,
+    Sample4,
+    Sample8,
+}
+
+impl MsaaSamples {
+    #[cfg(not(target_arch = "wasm32"))]
+    const ALL: [MsaaSamples; 4] = [
+        MsaaSamples::Off,
+        MsaaSamples::Sample2,
+        MsaaSamples::Sample4,
+        MsaaSamples::Sample8,
+    ];
+    /// webgl2 only multisamples 4x, anything else fails to create the pipelines
+    #[cfg(target_arch = "wasm32")]
+    const ALL: [MsaaSamples; 2] = [MsaaSamples::Off, MsaaSamples::Sample4];
+
+    /// The same or the closest sample count this platform can render with
+    fn supported(self) -> MsaaSamples {
+        if Self::ALL.contains(&self) {
+            self
+        } else {
+            MsaaSamples::Sample4
+        }
+    }
+
+    pub fn next(&self, direction: i32) -> MsaaSamples {
+        let current = Self::ALL.iter().position(|msaa| msaa == self).unwrap_or(0) as i32;
+        Self::ALL[(current + direction).clamp(0, Self::ALL.len() as i32 - 1) as usize]
+    }
+
+    fn into_msaa(self) -> Msaa {
+        match self {
+            MsaaSamples::Off => Msaa::Off,
+            MsaaSamples::Sample2 => Msaa::Sample2,
+            MsaaSamples::Sample4 => Msaa::Sample4,
+            MsaaSamples::Sample8 => Msaa::Sample8,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TonemappingMode {
+    None,
+    Reinhard,
+    AcesFitted,
+    AgX,
+    TonyMcMapface,
+    BlenderFilmic,
+}
+
+impl TonemappingMode {
+    const ALL: [TonemappingMode; 6] = [
+        TonemappingMode::None,
+        TonemappingMode::Reinhard,
+        TonemappingMode::AcesFitted,
+        TonemappingMode::AgX,
+        TonemappingMode::TonyMcMapface,
+        TonemappingMode::BlenderFilmic,
+    ];
+
+    pub fn next(&self, direction: i32) -> TonemappingMode {
+        let current = Self::ALL.iter().position(|mode| mode == self).unwrap_or(0) as i32;
+        Self::ALL[(current + direction).rem_euclid(Self::ALL.len() as i32) as usize]
+    }
+
+    fn into_tonemapping(self) -> Tonemapping {
+        match self {
+            TonemappingMode::None => Tonemapping::None,
+            TonemappingMode::Reinhard => Tonemapping::Reinhard,
+            TonemappingMode::AcesFitted => Tonemapping::AcesFitted,
+            TonemappingMode::AgX => Tonemapping::AgX,
+            TonemappingMode::TonyMcMapface => Tonemapping::TonyMcMapface,
+            TonemappingMode::BlenderFilmic => Tonemapping::BlenderFilmic,
+        }
+    }
+}
+
+#[derive(Resource, Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct GraphicsSettings {
+    pub preset: GraphicsPreset,
+    pub msaa: MsaaSamples,
+    pub shadows: bool,
+    /// width and height of every shadow map in texels
+    pub shadow_map_size: usize,
+    /// shadow maps the sun splits the view distance into
+    pub shadow_cascades: usize,
+    pub fog: bool,
+    pub tonemapping: TonemappingMode,
+    /// segments around a ball, half as many from pole to pole
+    pub sphere_detail: usize,
+}
+
+impl Default for GraphicsSettings {
+    fn default() -> Self {
+        // webgl2 has no 8x msaa and browsers often run on integrated graphics
+        if cfg!(target_arch = "wasm32") {
+            GraphicsPreset::Low.settings()
+        } else {
+            GraphicsPreset::High.settings()
+        }
+    }
+}
+
+/// A ball shaped mesh, rebuilt with [`GraphicsSettings::sphere_detail`] segments
+#[derive(Component, Clone, Copy, Debug)]
+pub struct SphereMesh {
+    pub radius: f32,
+}
+
+fn apply_render_settings(
+    graphics_settings: Res<GraphicsSettings>,
+    mut msaa: ResMut<Msaa>,
+    mut directional_shadow_map: ResMut<DirectionalLightShadowMap>,
+    mut point_shadow_map: ResMut<PointLightShadowMap>,
+) {
+    if !graphics_settings.is_changed() {
+        return;
+    }
+    msaa.set_if_neq(graphics_settings.msaa.into_msaa());
+    if directional_shadow_map.size != graphics_settings.shadow_map_size {
+        directional_shadow_map.size = graphics_settings.shadow_map_size;
+    }
+    // point lights render six maps each, keep them smaller
+    let point_size = (graphics_settings.shadow_map_size / 2).max(256);
+    if point_shadow_map.size != point_size {
+        point_shadow_map.size = point_size;
+    }
+}
+
+fn apply_light_settings(
+    mut commands: Commands,
+    graphics_settings: Res<GraphicsSettings>,
+    mut directional_lights: Query<(Entity, &mut DirectionalLight)>,
+    mut point_lights: Query<&mut PointLight>,
+    mut spot_lights: Query<&mut SpotLight>,
+    added: Query<(), Or<(Added<DirectionalLight>, Added<PointLight>, Added<SpotLight>)>>,
+) {
+    if !graphics_settings.is_changed() && added.is_empty() {
+        return;
+    }
+    let shadows = graphics_settings.shadows;
+
+    for (entity, mut light) in &mut directional_lights {
+        light.shadows_enabled = shadows;
+        let cascades: CascadeShadowConfig = CascadeShadowConfigBuilder {
+            num_cascades: graphics_settings.shadow_cascades.max(1),
+            first_cascade_far_bound: 200.0 / graphics_settings.shadow_cascades.max(1) as f32,
+            maximum_distance: 280.0,
+            ..default()
+        }
+        .into();
+        commands.entity(entity).insert(cascades);
+    }
+    for mut light in &mut point_lights {
+        light.shadows_enabled = shadows;
+    }
+    for mut light in &mut spot_lights {
+        light.shadows_enabled = shadows;
+    }
+}
+
+fn apply_camera_settings(
+    mut commands: Commands,
+    graphics_settings: Res<GraphicsSettings>,
+    cameras: Query<(Entity, Option<&FogSettings>), With<MainCamera>>,
+    added: Query<(), Added<MainCamera>>,
+) {
+    if !graphics_settings.is_changed() && added.is_empty() {
+        return;
+    }
+    for (entity, fog) in &cameras {
+        let mut camera = commands.entity(entity);
+        camera.insert(graphics_settings.tonemapping.into_tonemapping());
+        // the lighting plugin colors the fog as soon as it is back
+        match (graphics_settings.fog, fog.is_some()) {
+            (true, false) => {
+                camera.insert(FogSettings {
+                    directional_light_exponent: 30.0,
+                    ..default()
+                });
+            }
+            (false, true) => {
+                camera.remove::<FogSettings>();
+            }
+            _ => {}
+        }
+    }
+}
+
+fn apply_sphere_detail(
+    graphics_settings: Res<GraphicsSettings>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut spheres: Query<(Ref<SphereMesh>, &mut Handle<Mesh>)>,
+    mut sphere_meshes: Local<HashMap<u32, Handle<Mesh>>>,
+) {
+    let rebuild_all = graphics_settings.is_changed();
+    if rebuild_all {
+        sphere_meshes.clear();
+    }
+
+    for (sphere, mut mesh) in &mut spheres {
+        if !(rebuild_all || sphere.is_added()) {
+            continue;
+        }
+        // balls of the same size share one mesh
+        *mesh = sphere_meshes
+            .entry(sphere.radius.to_bits())
+            .or_insert_with(|| {
+                meshes.add(Mesh::from(shape::UVSphere {
+                    radius: sphere.radius,
+                    sectors: graphics_settings.sphere_detail.max(4),
+                    stacks: (graphics_settings.sphere_detail / 2).max(2),
+                }))
+            })
+            .clone();
+    }
+}
+
+fn save_graphics_settings(graphics_settings: Res<GraphicsSettings>) {
+    if graphics_settings.is_changed() && !graphics_settings.is_added() {
+        save_config(GRAPHICS_CONFIG, &*graphics_settings);
+    }
+}
@@ -0,0 +1,130 @@
+use crate::{
+    actions::{Action, ActionState},
+    history::RecordHistory,
+    interaction::{CursorState, InteractionTool, PointerInput, PointerSet},
+    GameState,
+};
+use bevy::prelude::*;
+use bevy_rapier3d::prelude::*;
+
+pub struct HammerPlugin;
+
+/// This plugin strikes balls with an impulse instead of dragging them,
+/// the longer the grab button is held the harder the strike
+impl Plugin for HammerPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(HammerSettings::default())
+            .insert_resource(HammerCharge::default())
+            .add_systems(
+                Update,
+                (
+                    toggle_hammer_axis.run_if(in_state(GameState::Playing)),
+                    charge_hammer.run_if(in_state(GameState::Playing)),
+                    strike_hammer.run_if(in_state(GameState::Playing)),
+                )
+                    .chain()
+                    .after(PointerSet),
+            );
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum HammerAxis {
+    /// straight away from the camera through the pointer
+    #[default]
+    ViewRay,
+    /// along the row of balls, towards the side the camera looks at
+    CradleAxis,
+}
+
+#[derive(Resource)]
+pub struct HammerSettings {
+    pub axis: HammerAxis,
+    /// impulse gained per second the button is held
+    pub charge_rate: f32,
+    pub min_impulse: f32,
+    pub max_impulse: f32,
+}
+
+impl Default for HammerSettings {
+    fn default() -> Self {
+        HammerSettings {
+            axis: HammerAxis::default(),
+            charge_rate: 80.0,
+            min_impulse: 10.0,
+            max_impulse: 160.0,
+        }
+    }
+}
+
+impl HammerSettings {
+    pub fn impulse(&self, held_secs: f32) -> f32 {
+        (self.min_impulse + held_secs * self.charge_rate).min(self.max_impulse)
+    }
+
+    pub fn direction(&self, ray_direction: Vec3) -> Vec3 {
+        match self.axis {
+            HammerAxis::ViewRay => ray_direction.normalize_or_zero(),
+            HammerAxis::CradleAxis => Vec3::X * ray_direction.x.signum(),
+        }
+    }
+}
+
+/// The ball being wound up for a strike
+#[derive(Resource, Default)]
+struct HammerCharge {
+    target: Option<Entity>,
+    held_secs: f32,
+}
+
+fn toggle_hammer_axis(actions: Res<ActionState>, mut hammer_settings: ResMut<HammerSettings>) {
+    if actions.just_pressed(Action::ToggleHammerAxis) {
+        hammer_settings.axis = match hammer_settings.axis {
+            HammerAxis::ViewRay => HammerAxis::CradleAxis,
+            HammerAxis::CradleAxis => HammerAxis::ViewRay,
+        };
+        info!("hammer axis: {:?}", hammer_settings.axis);
+    }
+}
+
+fn charge_hammer(
+    time: Res<Time>,
+    tool: Res<InteractionTool>,
+    pointer: Res<PointerInput>,
+    cursor_state: Res<CursorState>,
+    mut hammer_charge: ResMut<HammerCharge>,
+) {
+    if *tool != InteractionTool::Hammer {
+        hammer_charge.target = None;
+        return;
+    }
+
+    if pointer.just_pressed {
+        hammer_charge.target = cursor_state.current_hit_entity;
+        hammer_charge.held_secs = 0.0;
+    } else if pointer.pressed && hammer_charge.target.is_some() {
+        hammer_charge.held_secs += time.delta_seconds();
+    }
+}
+
+fn strike_hammer(
+    pointer: Res<PointerInput>,
+    cursor_state: Res<CursorState>,
+    hammer_settings: Res<HammerSettings>,
+    mut hammer_charge: ResMut<HammerCharge>,
+    mut impulses: Query<&mut ExternalImpulse>,
+    mut history: EventWriter<RecordHistory>,
+) {
+    if !pointer.just_released {
+        return;
+    }
+    let Some(target) = hammer_charge.target.take() else {
+        return;
+    };
+
+    if let Ok(mut external_impulse) = impulses.get_mut(target) {
+        history.send(RecordHistory);
+        let direction = hammer_settings.direction(cursor_state.current_ray_direction);
+        external_impulse.impulse = direction * hammer_settings.impulse(hammer_charge.held_secs);
+    }
+}
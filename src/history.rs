@@ -0,0 +1,214 @@
+use crate::{
+    actions::{Action, ActionState},
+    cradle::Ball,
+    interaction::{PointerInput, PointerSet},
+    launcher::{spawn_projectile, LauncherSettings, Projectile},
+    lift::{lift_ball, release_ball, Lifted},
+    materials::BallFinishes,
+    GameState,
+};
+use bevy::prelude::*;
+use bevy_rapier3d::prelude::*;
+
+pub struct HistoryPlugin;
+
+/// This plugin remembers the cradle before every interaction, so it can be undone and redone
+impl Plugin for HistoryPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(History::default())
+            .add_event::<RecordHistory>()
+            .add_systems(
+                PreUpdate,
+                capture_scene.run_if(in_state(GameState::Playing)),
+            )
+            .add_systems(
+                Update,
+                undo_redo
+                    .run_if(in_state(GameState::Playing))
+                    .after(PointerSet),
+            )
+            .add_systems(
+                PostUpdate,
+                record_history.run_if(in_state(GameState::Playing)),
+            );
+    }
+}
+
+/// Sent by anything about to change the cradle, the state from the start
+/// of the frame becomes the next undo step
+#[derive(Event, Default)]
+pub struct RecordHistory;
+
+#[derive(Clone)]
+struct BallSnapshot {
+    index: usize,
+    transform: Transform,
+    velocity: Velocity,
+    lifted: Option<Lifted>,
+}
+
+#[derive(Clone)]
+struct ProjectileSnapshot {
+    settings: LauncherSettings,
+    /// what is left of its life, so undoing does not keep it around for ever
+    lifetime: Timer,
+    transform: Transform,
+    velocity: Velocity,
+}
+
+#[derive(Clone, Default)]
+struct SceneSnapshot {
+    balls: Vec<BallSnapshot>,
+    projectiles: Vec<ProjectileSnapshot>,
+    launcher: LauncherSettings,
+    finishes: BallFinishes,
+}
+
+#[derive(Resource)]
+pub struct History {
+    /// undo steps kept before the oldest is dropped
+    pub capacity: usize,
+    undo_stack: Vec<SceneSnapshot>,
+    redo_stack: Vec<SceneSnapshot>,
+    /// the scene at the start of the current frame
+    frame: SceneSnapshot,
+}
+
+impl Default for History {
+    fn default() -> Self {
+        History {
+            capacity: 64,
+            undo_stack: Vec::new(),
+            redo_stack: Vec::new(),
+            frame: SceneSnapshot::default(),
+        }
+    }
+}
+
+impl History {
+    fn record(&mut self) {
+        self.undo_stack.push(self.frame.clone());
+        if self.undo_stack.len() > self.capacity {
+            self.undo_stack.remove(0);
+        }
+        self.redo_stack.clear();
+    }
+
+    fn undo(&mut self) -> Option<SceneSnapshot> {
+        let snapshot = self.undo_stack.pop()?;
+        self.redo_stack.push(self.frame.clone());
+        Some(snapshot)
+    }
+
+    fn redo(&mut self) -> Option<SceneSnapshot> {
+        let snapshot = self.redo_stack.pop()?;
+        self.undo_stack.push(self.frame.clone());
+        Some(snapshot)
+    }
+}
+
+fn capture_scene(
+    mut history: ResMut<History>,
+    launcher_settings: Res<LauncherSettings>,
+    ball_finishes: Res<BallFinishes>,
+    balls: Query<(&Ball, &Transform, &Velocity, Option<&Lifted>)>,
+    projectiles: Query<(&Projectile, &Transform, &Velocity)>,
+) {
+    history.frame = SceneSnapshot {
+        balls: balls
+            .iter()
+            .map(|(ball, transform, velocity, lifted)| BallSnapshot {
+                index: ball.index,
+                transform: *transform,
+                velocity: *velocity,
+                lifted: lifted.copied(),
+            })
+            .collect(),
+        projectiles: projectiles
+            .iter()
+            .map(|(projectile, transform, velocity)| ProjectileSnapshot {
+                settings: projectile.settings.clone(),
+                lifetime: projectile.lifetime.clone(),
+                transform: *transform,
+                velocity: *velocity,
+            })
+            .collect(),
+        launcher: launcher_settings.clone(),
+        finishes: ball_finishes.clone(),
+    };
+}
+
+fn record_history(mut events: EventReader<RecordHistory>, mut history: ResMut<History>) {
+    if events.is_empty() {
+        return;
+    }
+    events.clear();
+    history.record();
+}
+
+fn undo_redo(
+    mut commands: Commands,
+    actions: Res<ActionState>,
+    pointer: Res<PointerInput>,
+    mut history: ResMut<History>,
+    mut launcher_settings: ResMut<LauncherSettings>,
+    mut ball_finishes: ResMut<BallFinishes>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    balls: Query<(Entity, &Ball)>,
+    projectiles: Query<Entity, With<Projectile>>,
+) {
+    // a ball held by the pointer is owned by the drag joint until it is let go
+    if pointer.pressed {
+        return;
+    }
+
+    let snapshot = if actions.just_pressed(Action::Undo) {
+        history.undo()
+    } else if actions.just_pressed(Action::Redo) {
+        history.redo()
+    } else {
+        return;
+    };
+    let Some(snapshot) = snapshot else {
+        return;
+    };
+
+    for (entity, ball) in &balls {
+        let Some(ball_snapshot) = snapshot.balls.iter().find(|b| b.index == ball.index) else {
+            continue;
+        };
+        match ball_snapshot.lifted {
+            Some(lifted) => lift_ball(&mut commands, entity, lifted.angle),
+            None => release_ball(&mut commands, entity),
+        }
+        commands
+            .entity(entity)
+            .insert((ball_snapshot.transform, ball_snapshot.velocity));
+    }
+
+    for entity in &projectiles {
+        commands.entity(entity).despawn_recursive();
+    }
+    for projectile in &snapshot.projectiles {
+        let entity = spawn_projectile(
+            &mut commands,
+            &mut meshes,
+            &mut materials,
+            &projectile.settings,
+            projectile.transform.translation,
+            projectile.velocity.linvel,
+        );
+        commands.entity(entity).insert((
+            projectile.transform,
+            projectile.velocity,
+            Projectile {
+                lifetime: projectile.lifetime.clone(),
+                settings: projectile.settings.clone(),
+            },
+        ));
+    }
+
+    launcher_settings.set_if_neq(snapshot.launcher);
+    ball_finishes.set_if_neq(snapshot.finishes);
+}
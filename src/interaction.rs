@@ -1,7 +1,19 @@
-use bevy::{input::mouse::MouseMotion, prelude::*, window::PrimaryWindow};
+use std::collections::VecDeque;
+
+use bevy::{
+    asset::HandleId, input::mouse::MouseMotion, prelude::*, utils::HashMap, window::PrimaryWindow,
+};
 use bevy_rapier3d::prelude::*;
 
-use crate::GameState;
+use crate::{
+    actions::{Action, ActionState, InputBinding, InputMap},
+    camera::MainCamera,
+    collision::cursor_groups,
+    gamepad::GamepadSelected,
+    history::RecordHistory,
+    materials::BallEmissive,
+    GameState,
+};
 
 pub struct InteractionPlugin;
 
@@ -9,19 +21,63 @@ pub struct InteractionPlugin;
 impl Plugin for InteractionPlugin {
     fn build(&self, app: &mut App) {
         app.insert_resource(CursorState::default())
+            .insert_resource(ReleaseSettings::default())
+            .insert_resource(PointerHistory::default())
+            .insert_resource(PointerInput::default())
+            .insert_resource(InteractionTool::default())
+            .init_resource::<HighlightColors>()
             .add_systems(
                 Update,
                 (
+                    update_pointer_input.run_if(in_state(GameState::Playing)),
                     my_cursor_system.run_if(in_state(GameState::Playing)),
+                )
+                    .chain()
+                    .in_set(PointerSet),
+            )
+            .add_systems(
+                Update,
+                (
+                    cycle_interaction_tool.run_if(in_state(GameState::Playing)),
                     handle_drag_selection.run_if(in_state(GameState::Playing)),
-                    handle_drag_release.run_if(in_state(GameState::Playing)),
                     handle_drag.run_if(in_state(GameState::Playing)),
-                ),
+                    track_pointer_history.run_if(in_state(GameState::Playing)),
+                    handle_drag_release.run_if(in_state(GameState::Playing)),
+                    toggle_release_mode.run_if(in_state(GameState::Playing)),
+                    highlight_balls.run_if(in_state(GameState::Playing)),
+                    update_cursor_icon.run_if(in_state(GameState::Playing)),
+                )
+                    .chain()
+                    .after(PointerSet),
             )
             .add_systems(OnEnter(GameState::Playing), setup_cursor_entity);
     }
 }
 
+/// Pointer position and picking, everything reacting to the pointer runs after this
+#[derive(SystemSet, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct PointerSet;
+
+/// What pressing the grab action on a ball does
+#[derive(Resource, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum InteractionTool {
+    /// hold the ball with a joint and move it around
+    #[default]
+    Drag,
+    /// strike the ball, see the hammer plugin
+    Hammer,
+}
+
+fn cycle_interaction_tool(actions: Res<ActionState>, mut tool: ResMut<InteractionTool>) {
+    if actions.just_pressed(Action::CycleTool) {
+        *tool = match *tool {
+            InteractionTool::Drag => InteractionTool::Hammer,
+            InteractionTool::Hammer => InteractionTool::Drag,
+        };
+        info!("tool: {:?}", *tool);
+    }
+}
+
 fn setup_cursor_entity(mut commands: Commands) {
     commands.spawn((
         TransformBundle::from(Transform::from_xyz(0.0, 0.0, 0.0)),
@@ -31,99 +87,415 @@ fn setup_cursor_entity(mut commands: Commands) {
 }
 
 fn handle_drag_selection(
-    buttons: Res<Input<MouseButton>>,
+    pointer: Res<PointerInput>,
+    actions: Res<ActionState>,
+    tool: Res<InteractionTool>,
     mut commands: Commands,
     mut cursor_state: ResMut<CursorState>,
     transforms: Query<&Transform, Without<CursorInteractor>>,
     mut cursor_interactor_q: Query<(Entity, &mut Transform), With<CursorInteractor>>,
+    mut history: EventWriter<RecordHistory>,
 ) {
-    if buttons.just_pressed(MouseButton::Left) {
-        let (cursor_entity, mut cursor_transform) = cursor_interactor_q.single_mut();
-
-        if cursor_state.current_hit_entity.is_some() {
-            cursor_state.drag_entity = cursor_state.current_hit_entity;
-            if let Some(drag_entity) = cursor_state.drag_entity {
-                if let Ok(drag_transform) = transforms.get(drag_entity) {
-                    cursor_transform.translation = drag_transform.translation;
-                    cursor_transform.rotation = drag_transform.rotation;
-                }
+    if !pointer.just_pressed || *tool != InteractionTool::Drag {
+        return;
+    }
 
-                let unlocked_axis = Vec3::X;
-                let fixed_joint = RevoluteJointBuilder::new(unlocked_axis).build();
-                let joint = ImpulseJoint::new(cursor_entity, fixed_joint);
+    let Some(hit_entity) = cursor_state.current_hit_entity else {
+        cursor_state.selected_entities.clear();
+        return;
+    };
 
-                commands.entity(drag_entity).with_children(|parent| {
-                    parent.spawn((joint, CursorInteractorJoint::default()));
-                });
-            }
-        }
+    // shift-click builds up a group of balls to lift together
+    if actions.pressed(Action::MultiSelect) {
+        cursor_state.toggle_selected(hit_entity);
+        return;
     }
+
+    history.send(RecordHistory);
+    let drag_entities = if cursor_state.selected_entities.contains(&hit_entity) {
+        cursor_state.selected_entities.clone()
+    } else {
+        cursor_state.selected_entities.clear();
+        vec![hit_entity]
+    };
+
+    let (cursor_entity, mut cursor_transform) = cursor_interactor_q.single_mut();
+    if let Ok(hit_transform) = transforms.get(hit_entity) {
+        cursor_transform.translation = hit_transform.translation;
+        cursor_transform.rotation = hit_transform.rotation;
+    }
+
+    for &drag_entity in &drag_entities {
+        let Ok(drag_transform) = transforms.get(drag_entity) else {
+            continue;
+        };
+        // every ball keeps its offset to the cursor, so the group moves as one rigid body
+        let offset = cursor_transform.rotation.inverse()
+            * (drag_transform.translation - cursor_transform.translation);
+
+        let unlocked_axis = Vec3::X;
+        let fixed_joint = RevoluteJointBuilder::new(unlocked_axis)
+            .local_anchor1(offset)
+            .build();
+        let joint = ImpulseJoint::new(cursor_entity, fixed_joint);
+
+        commands.entity(drag_entity).with_children(|parent| {
+            parent.spawn((joint, CursorInteractorJoint::default()));
+        });
+    }
+    cursor_state.drag_entities = drag_entities;
 }
 
 fn handle_drag_release(
-    buttons: Res<Input<MouseButton>>,
+    pointer: Res<PointerInput>,
     cursor_joint_q: Query<Entity, With<CursorInteractorJoint>>,
     mut commands: Commands,
+    mut cursor_state: ResMut<CursorState>,
+    release_settings: Res<ReleaseSettings>,
+    pointer_history: Res<PointerHistory>,
+    mut velocities: Query<&mut Velocity>,
 ) {
-    if buttons.just_released(MouseButton::Left) {
+    if pointer.just_released {
         for cursor_joint in &cursor_joint_q {
             commands.entity(cursor_joint).despawn();
         }
+
+        let flick_velocity = release_settings.flick_velocity(&pointer_history);
+        for drag_entity in cursor_state.drag_entities.drain(..) {
+            if release_settings.mode == ReleaseMode::Flick {
+                if let Ok(mut velocity) = velocities.get_mut(drag_entity) {
+                    velocity.linvel = flick_velocity;
+                    velocity.angvel = Vec3::ZERO;
+                }
+            }
+        }
+    }
+}
+
+/// Samples where the pointer ray crosses the plane the balls are dragged in. The cursor
+/// interactor lags behind by a frame rate dependent amount, so it is not used for this
+fn track_pointer_history(
+    pointer: Res<PointerInput>,
+    time: Res<Time>,
+    release_settings: Res<ReleaseSettings>,
+    cursor_state: Res<CursorState>,
+    mut pointer_history: ResMut<PointerHistory>,
+    cursor_interactor_transform_q: Query<&Transform, With<CursorInteractor>>,
+) {
+    if pointer.just_pressed {
+        pointer_history.samples.clear();
+    }
+
+    if pointer.pressed {
+        let cursor_transform = cursor_interactor_transform_q.single();
+        let now = time.elapsed_seconds();
+        // the drag only moves the cursor in x and y
+        let plane_z = cursor_transform.translation.z;
+        let origin = cursor_state.current_cursor_position;
+        let direction = cursor_state.current_ray_direction;
+        if direction.z.abs() > f32::EPSILON {
+            let distance = (plane_z - origin.z) / direction.z;
+            if distance > 0.0 {
+                pointer_history
+                    .samples
+                    .push_back((now, origin + direction * distance));
+            }
+        }
+
+        //only keep the samples we need for the velocity estimate
+        while let Some(&(sample_time, _)) = pointer_history.samples.front() {
+            if now - sample_time > release_settings.sample_window {
+                pointer_history.samples.pop_front();
+            } else {
+                break;
+            }
+        }
+    }
+}
+
+fn toggle_release_mode(actions: Res<ActionState>, mut release_settings: ResMut<ReleaseSettings>) {
+    if actions.just_pressed(Action::ToggleReleaseMode) {
+        release_settings.mode = match release_settings.mode {
+            ReleaseMode::Joint => ReleaseMode::Flick,
+            ReleaseMode::Flick => ReleaseMode::Joint,
+        };
+        info!("release mode: {:?}", release_settings.mode);
     }
 }
 
 fn handle_drag(
-    buttons: Res<Input<MouseButton>>,
+    pointer: Res<PointerInput>,
     time: Res<Time>,
-    mut motion_evr: EventReader<MouseMotion>,
     mut cursor_interactor_transform_q: Query<&mut Transform, With<CursorInteractor>>,
 ) {
-    if buttons.pressed(MouseButton::Left) {
+    if pointer.pressed {
         let mut cursor_transform = cursor_interactor_transform_q.single_mut();
-        for ev in motion_evr.iter() {
-            cursor_transform.translation.x += ev.delta.x * time.delta_seconds();
-            cursor_transform.translation.y += ev.delta.y * -1. * time.delta_seconds();
+        cursor_transform.translation.x += pointer.delta.x * time.delta_seconds();
+        cursor_transform.translation.y += pointer.delta.y * -1. * time.delta_seconds();
+    }
+}
+
+/// Merges the mouse and a single finger touch into one pointer, so picking and
+/// dragging work the same on desktop, mobile and touchscreen browsers
+fn update_pointer_input(
+    actions: Res<ActionState>,
+    input_map: Res<InputMap>,
+    touches: Res<Touches>,
+    mut motion_evr: EventReader<MouseMotion>,
+    windows: Query<&Window, With<PrimaryWindow>>,
+    mut pointer: ResMut<PointerInput>,
+) {
+    let was_pressed = pointer.pressed;
+    let mouse_delta: Vec2 = motion_evr.iter().map(|ev| ev.delta).sum();
+    let touch_count = touches.iter().count();
+
+    if let Some(touch_id) = pointer.touch_id {
+        match touches.get_pressed(touch_id) {
+            Some(touch) if touch_count == 1 && actions.pressed(Action::Grab) => {
+                let position = touch.position();
+                pointer.delta = pointer
+                    .position
+                    .map_or(Vec2::ZERO, |last_position| position - last_position);
+                pointer.position = Some(position);
+            }
+            // the finger was lifted, or a second finger turned this into a camera gesture
+            _ => {
+                pointer.touch_id = None;
+                pointer.position = None;
+                pointer.delta = Vec2::ZERO;
+                pointer.pressed = false;
+            }
+        }
+    } else if touch_count == 1 && input_map.is_bound(Action::Grab, InputBinding::Touch) {
+        if let Some(touch) = touches
+            .iter_just_pressed()
+            .next()
+            .filter(|_| actions.pressed(Action::Grab))
+        {
+            pointer.touch_id = Some(touch.id());
+            pointer.position = Some(touch.position());
+            pointer.delta = Vec2::ZERO;
+            pointer.pressed = true;
+        }
+    } else if touch_count == 0 {
+        pointer.position = windows
+            .get_single()
+            .ok()
+            .and_then(|window| window.cursor_position());
+        pointer.delta = mouse_delta;
+        pointer.pressed = actions.pressed(Action::Grab);
+    } else {
+        pointer.position = None;
+        pointer.delta = Vec2::ZERO;
+        pointer.pressed = false;
+    }
+
+    pointer.just_pressed = pointer.pressed && !was_pressed;
+    pointer.just_released = !pointer.pressed && was_pressed;
+}
+
+fn highlight_balls(
+    cursor_state: Res<CursorState>,
+    highlight_colors: Res<HighlightColors>,
+    balls: Query<(Entity, Option<&GamepadSelected>), With<Grabbable>>,
+    children: Query<&Children>,
+    // parts get their glow from the materials plugin, before that they are left alone
+    ball_materials: Query<(Entity, &Handle<StandardMaterial>, &BallEmissive)>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    // the material and highlight each part was last given
+    mut applied: Local<HashMap<Entity, (HandleId, Color)>>,
+) {
+    applied.retain(|part, _| ball_materials.contains(*part));
+    for (entity, gamepad_selected) in &balls {
+        let highlight = if cursor_state.drag_entities.contains(&entity) {
+            highlight_colors.grabbed
+        } else if cursor_state.selected_entities.contains(&entity) {
+            highlight_colors.selected
+        } else if cursor_state.current_hit_entity == Some(entity) || gamepad_selected.is_some() {
+            highlight_colors.hovered
+        } else {
+            Color::BLACK
+        };
+
+        // a ball from a glTF model has its meshes below it
+        let ball_parts = ball_materials
+            .iter_many(std::iter::once(entity).chain(children.iter_descendants(entity)));
+        for (part, material_handle, base) in ball_parts {
+            // only touch the material when it changes, every change re-uploads it
+            if applied.get(&part) == Some(&(material_handle.id(), highlight)) {
+                continue;
+            }
+            if let Some(material) = materials.get_mut(material_handle) {
+                // the highlight adds to the glow of the finish
+                material.emissive = base.0 + highlight;
+                applied.insert(part, (material_handle.id(), highlight));
+            }
+        }
+    }
+}
+
+fn update_cursor_icon(
+    cursor_state: Res<CursorState>,
+    mut windows: Query<&mut Window, With<PrimaryWindow>>,
+) {
+    let icon = if !cursor_state.drag_entities.is_empty() {
+        CursorIcon::Grabbing
+    } else if cursor_state.current_hit_entity.is_some() {
+        CursorIcon::Grab
+    } else {
+        CursorIcon::Default
+    };
+
+    if let Ok(mut window) = windows.get_single_mut() {
+        if window.cursor.icon != icon {
+            window.cursor.icon = icon;
         }
     }
 }
 
 fn my_cursor_system(
-    // need to get window dimensions
-    windows: Query<&Window, With<PrimaryWindow>>,
+    // screen position of the mouse or the dragging finger
+    pointer: Res<PointerInput>,
     // query to get camera transform
-    camera_q: Query<(&Camera, &GlobalTransform)>,
+    camera_q: Query<(&Camera, &GlobalTransform), With<MainCamera>>,
     rapier_context: Res<RapierContext>,
     mut cursor_state: ResMut<CursorState>,
+    grabbables: Query<(), With<Grabbable>>,
 ) {
     // get the camera info and transform
     // assuming there is exactly one main camera entity, so query::single() is OK
     let (camera, camera_transform) = camera_q.single();
 
-    let window = windows.single();
-    // check if the cursor is inside the window and get its position
+    // check if the pointer is inside the window and get its position
     // then, ask bevy to convert into world coordinates, and truncate to discard Z
-    if let Some(cursor_position) = window.cursor_position() {
-        if let Some(ray) = camera.viewport_to_world(camera_transform, cursor_position) {
-            cursor_state.current_cursor_position = ray.origin;
-
-            if let Some((hit_entity, _)) =
-                rapier_context.cast_ray(ray.origin, ray.direction, 1000.0, true, QueryFilter::new())
-            {
-                cursor_state.current_hit_entity = Some(hit_entity);
-            } else {
-                cursor_state.current_hit_entity = None;
-            }
+    let Some(cursor_position) = pointer.position else {
+        cursor_state.current_hit_entity = None;
+        return;
+    };
+    if let Some(ray) = camera.viewport_to_world(camera_transform, cursor_position) {
+        cursor_state.current_cursor_position = ray.origin;
+        cursor_state.current_ray_direction = ray.direction;
+
+        let is_grabbable = |entity: Entity| grabbables.contains(entity);
+        let filter = QueryFilter::new()
+            .groups(cursor_groups())
+            .predicate(&is_grabbable);
+
+        if let Some((hit_entity, _)) =
+            rapier_context.cast_ray(ray.origin, ray.direction, 1000.0, true, filter)
+        {
+            cursor_state.current_hit_entity = Some(hit_entity);
+        } else {
+            cursor_state.current_hit_entity = None;
+        }
+    }
+}
+
+/// Pointer state shared by the mouse and touch input
+#[derive(Resource, Default)]
+pub struct PointerInput {
+    pub position: Option<Vec2>,
+    pub delta: Vec2,
+    pub pressed: bool,
+    pub just_pressed: bool,
+    pub just_released: bool,
+    touch_id: Option<u64>,
+}
+
+#[derive(Resource, Default)]
+pub struct CursorState {
+    pub current_hit_entity: Option<Entity>,
+    /// balls picked with shift-click, grabbing any of them lifts the whole group
+    selected_entities: Vec<Entity>,
+    drag_entities: Vec<Entity>,
+    pub current_cursor_position: Vec3,
+    pub current_ray_direction: Vec3,
+}
+
+impl CursorState {
+    fn toggle_selected(&mut self, entity: Entity) {
+        if let Some(index) = self.selected_entities.iter().position(|e| *e == entity) {
+            self.selected_entities.remove(index);
+        } else {
+            self.selected_entities.push(entity);
+        }
+    }
+}
+
+/// How a dragged ball is let go of when the mouse button is released
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ReleaseMode {
+    /// the ball keeps whatever velocity the drag joint gave it
+    #[default]
+    Joint,
+    /// the ball is thrown with the pointer velocity of the last few frames
+    Flick,
+}
+
+#[derive(Resource)]
+pub struct ReleaseSettings {
+    pub mode: ReleaseMode,
+    /// seconds of pointer movement used to estimate the flick velocity
+    pub sample_window: f32,
+    pub velocity_scale: f32,
+    pub max_speed: f32,
+}
+
+impl Default for ReleaseSettings {
+    fn default() -> Self {
+        ReleaseSettings {
+            mode: ReleaseMode::default(),
+            sample_window: 0.1,
+            velocity_scale: 1.0,
+            max_speed: 40.0,
         }
     }
 }
 
+impl ReleaseSettings {
+    fn flick_velocity(&self, pointer_history: &PointerHistory) -> Vec3 {
+        let (Some((start_time, start)), Some((end_time, end))) = (
+            pointer_history.samples.front(),
+            pointer_history.samples.back(),
+        ) else {
+            return Vec3::ZERO;
+        };
+
+        let elapsed = end_time - start_time;
+        if elapsed <= f32::EPSILON {
+            return Vec3::ZERO;
+        }
+
+        ((*end - *start) / elapsed * self.velocity_scale).clamp_length_max(self.max_speed)
+    }
+}
+
 #[derive(Resource, Default)]
-struct CursorState {
-    current_hit_entity: Option<Entity>,
-    drag_entity: Option<Entity>,
-    current_cursor_position: Vec3,
+struct PointerHistory {
+    samples: VecDeque<(f32, Vec3)>,
+}
+
+#[derive(Resource)]
+struct HighlightColors {
+    hovered: Color,
+    selected: Color,
+    grabbed: Color,
 }
 
+impl Default for HighlightColors {
+    fn default() -> Self {
+        HighlightColors {
+            hovered: Color::rgb(0.15, 0.25, 0.4),
+            selected: Color::rgb(0.1, 0.4, 0.15),
+            grabbed: Color::rgb(0.6, 0.35, 0.05),
+        }
+    }
+}
+
+/// Marks entities the cursor is allowed to pick up
+#[derive(Component, Default)]
+pub struct Grabbable {}
+
 #[derive(Component, Default)]
 struct CursorInteractor {}
 
@@ -0,0 +1,262 @@
+use crate::{
+    actions::{Action, ActionState},
+    audio::BallSound,
+    camera::MainCamera,
+    collision::ball_groups,
+    cradle::{cradle_center, Ball},
+    graphics::SphereMesh,
+    history::RecordHistory,
+    interaction::{CursorState, PointerInput, PointerSet},
+    GameState,
+};
+use bevy::prelude::*;
+use bevy_rapier3d::prelude::*;
+
+pub struct LauncherPlugin;
+
+/// This plugin fires extra spheres into the cradle, from the camera or from a cannon
+impl Plugin for LauncherPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(LauncherSettings::default())
+            .add_systems(
+                Update,
+                (
+                    toggle_launch_origin.run_if(in_state(GameState::Playing)),
+                    sync_cannon.run_if(in_state(GameState::Playing)),
+                    fire_projectile.run_if(in_state(GameState::Playing)),
+                    despawn_projectiles.run_if(in_state(GameState::Playing)),
+                )
+                    .chain()
+                    .after(PointerSet),
+            );
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum LaunchOrigin {
+    /// from the camera through the pointer
+    #[default]
+    Camera,
+    /// from the cannon at [`LauncherSettings::cannon_position`], aimed at the first ball
+    Cannon,
+}
+
+#[derive(Resource, Clone, Debug, PartialEq)]
+pub struct LauncherSettings {
+    pub origin: LaunchOrigin,
+    pub cannon_position: Vec3,
+    pub mass: f32,
+    pub speed: f32,
+    pub restitution: f32,
+    pub radius: f32,
+    /// seconds before a projectile is removed
+    pub lifetime: f32,
+    /// projectiles further than this from the cradle are removed
+    pub max_distance: f32,
+}
+
+impl Default for LauncherSettings {
+    fn default() -> Self {
+        LauncherSettings {
+            origin: LaunchOrigin::default(),
+            cannon_position: Vec3::new(-15.0, 5.0, 0.0),
+            mass: 8.0,
+            speed: 25.0,
+            restitution: 0.9,
+            radius: 1.0,
+            lifetime: 20.0,
+            max_distance: 150.0,
+        }
+    }
+}
+
+#[derive(Component)]
+pub struct Projectile {
+    pub lifetime: Timer,
+    /// what it was fired with, to fire it again when undoing
+    pub settings: LauncherSettings,
+}
+
+#[derive(Component)]
+struct Cannon;
+
+fn toggle_launch_origin(
+    actions: Res<ActionState>,
+    mut launcher_settings: ResMut<LauncherSettings>,
+    mut history: EventWriter<RecordHistory>,
+) {
+    if actions.just_pressed(Action::ToggleLaunchOrigin) {
+        history.send(RecordHistory);
+        launcher_settings.origin = match launcher_settings.origin {
+            LaunchOrigin::Camera => LaunchOrigin::Cannon,
+            LaunchOrigin::Cannon => LaunchOrigin::Camera,
+        };
+        info!("launch origin: {:?}", launcher_settings.origin);
+    }
+}
+
+/// The first ball of the row, the cannon aims at it
+fn cannon_target(balls: &Query<(&Ball, &Transform)>) -> Option<Vec3> {
+    balls
+        .iter()
+        .min_by_key(|(ball, _)| ball.index)
+        .map(|(_, transform)| transform.translation)
+}
+
+/// Shows the cannon while it is the launch origin and keeps it aimed
+fn sync_cannon(
+    mut commands: Commands,
+    launcher_settings: Res<LauncherSettings>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    balls: Query<(&Ball, &Transform), Without<Cannon>>,
+    mut cannons: Query<(Entity, &mut Transform), With<Cannon>>,
+) {
+    if launcher_settings.origin != LaunchOrigin::Cannon {
+        for (entity, _) in &cannons {
+            commands.entity(entity).despawn_recursive();
+        }
+        return;
+    }
+
+    let target = cannon_target(&balls).unwrap_or(Vec3::ZERO);
+    // the cylinder mesh points along y, turn that towards the target
+    let aim = Transform::from_translation(launcher_settings.cannon_position)
+        .looking_at(target, Vec3::Y)
+        * Transform::from_rotation(Quat::from_rotation_x(-std::f32::consts::FRAC_PI_2));
+
+    if let Ok((_, mut transform)) = cannons.get_single_mut() {
+        *transform = aim;
+    } else {
+        commands.spawn((
+            PbrBundle {
+                mesh: meshes.add(Mesh::from(shape::Cylinder {
+                    radius: 1.2,
+                    height: 4.0,
+                    ..default()
+                })),
+                material: materials.add(StandardMaterial {
+                    base_color: Color::DARK_GRAY,
+                    metallic: 0.8,
+                    perceptual_roughness: 0.4,
+                    ..default()
+                }),
+                transform: aim,
+                ..default()
+            },
+            Cannon,
+        ));
+    }
+}
+
+fn fire_projectile(
+    mut commands: Commands,
+    actions: Res<ActionState>,
+    launcher_settings: Res<LauncherSettings>,
+    pointer: Res<PointerInput>,
+    cursor_state: Res<CursorState>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    cameras: Query<&GlobalTransform, With<MainCamera>>,
+    balls: Query<(&Ball, &Transform)>,
+    mut history: EventWriter<RecordHistory>,
+) {
+    if !actions.just_pressed(Action::Fire) {
+        return;
+    }
+
+    let (start, direction) = match launcher_settings.origin {
+        LaunchOrigin::Camera => {
+            let Some(camera_transform) = cameras.iter().next() else {
+                return;
+            };
+            let direction = if pointer.position.is_some() {
+                cursor_state.current_ray_direction
+            } else {
+                camera_transform.forward()
+            };
+            // start in front of the camera so the projectile does not fill the view
+            (camera_transform.translation() + direction * 2.0, direction)
+        }
+        LaunchOrigin::Cannon => {
+            let start = launcher_settings.cannon_position;
+            let target = cannon_target(&balls).unwrap_or(Vec3::ZERO);
+            (start, (target - start).normalize_or_zero())
+        }
+    };
+
+    history.send(RecordHistory);
+    spawn_projectile(
+        &mut commands,
+        &mut meshes,
+        &mut materials,
+        &launcher_settings,
+        start,
+        direction * launcher_settings.speed,
+    );
+}
+
+pub fn spawn_projectile(
+    commands: &mut Commands,
+    meshes: &mut ResMut<Assets<Mesh>>,
+    materials: &mut ResMut<Assets<StandardMaterial>>,
+    launcher_settings: &LauncherSettings,
+    position: Vec3,
+    linvel: Vec3,
+) -> Entity {
+    commands
+        .spawn((
+            PbrBundle {
+                mesh: meshes.add(Mesh::from(shape::UVSphere {
+                    radius: launcher_settings.radius,
+                    ..default()
+                })),
+                material: materials.add(StandardMaterial {
+                    base_color: Color::rgb(0.8, 0.5, 0.2),
+                    perceptual_roughness: 0.3,
+                    metallic: 1.0,
+                    ..default()
+                }),
+                transform: Transform::from_translation(position),
+                ..default()
+            },
+            RigidBody::Dynamic,
+            Collider::ball(launcher_settings.radius),
+            ball_groups(),
+            ColliderMassProperties::Mass(launcher_settings.mass),
+            Restitution {
+                coefficient: launcher_settings.restitution,
+                combine_rule: CoefficientCombineRule::Min,
+            },
+            Velocity::linear(linvel),
+            Ccd::enabled(),
+            BallSound::default(),
+            ActiveEvents::COLLISION_EVENTS,
+            SphereMesh {
+                radius: launcher_settings.radius,
+            },
+            Projectile {
+                lifetime: Timer::from_seconds(launcher_settings.lifetime, TimerMode::Once),
+                settings: launcher_settings.clone(),
+            },
+        ))
+        .id()
+}
+
+fn despawn_projectiles(
+    mut commands: Commands,
+    time: Res<Time>,
+    actions: Res<ActionState>,
+    launcher_settings: Res<LauncherSettings>,
+    mut projectiles: Query<(Entity, &mut Projectile, &Transform)>,
+) {
+    let reset = actions.just_pressed(Action::Reset);
+    for (entity, mut projectile, transform) in &mut projectiles {
+        projectile.lifetime.tick(time.delta());
+        let out_of_scene =
+            transform.translation.distance(cradle_center()) > launcher_settings.max_distance;
+        if reset || out_of_scene || projectile.lifetime.finished() {
+            commands.entity(entity).despawn_recursive();
+        }
+    }
+}
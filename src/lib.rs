@@ -1,17 +1,57 @@
 #![allow(clippy::type_complexity)]
 
+mod actions;
+mod arrows;
 mod audio;
+mod camera;
+mod collision;
+mod config;
+mod contacts;
 mod cradle;
+mod cubemap;
+mod environment;
+mod forces;
+mod gamepad;
+mod graphics;
+mod hammer;
+mod history;
 mod interaction;
+mod launcher;
+mod lift;
+mod lighting;
 mod loading;
+mod materials;
 mod menu;
+mod models;
+mod replay;
 mod scene;
+mod settings;
+mod simulation;
+mod trails;
 
+use crate::actions::ActionsPlugin;
 use crate::audio::InternalAudioPlugin;
+use crate::camera::CameraControlPlugin;
+use crate::contacts::ContactsPlugin;
 use crate::cradle::CradlePlugin;
+use crate::environment::EnvironmentPlugin;
+use crate::forces::ForcesPlugin;
+use crate::gamepad::GamepadControlPlugin;
+use crate::graphics::GraphicsPlugin;
+use crate::hammer::HammerPlugin;
+use crate::history::HistoryPlugin;
 use crate::interaction::InteractionPlugin;
+use crate::launcher::LauncherPlugin;
+use crate::lift::LiftPlugin;
+use crate::lighting::LightingPlugin;
 use crate::loading::LoadingPlugin;
+use crate::materials::BallMaterialsPlugin;
 use crate::menu::MenuPlugin;
+use crate::models::ModelsPlugin;
+use crate::replay::ReplayPlugin;
+use crate::settings::SettingsPlugin;
+use crate::simulation::SimulationPlugin;
+use crate::trails::TrailsPlugin;
 
 use bevy::app::App;
 #[cfg(debug_assertions)]
@@ -37,14 +77,35 @@ pub struct GamePlugin;
 
 impl Plugin for GamePlugin {
     fn build(&self, app: &mut App) {
-        app.add_state::<GameState>().add_plugins((
-            LoadingPlugin,
-            MenuPlugin,
-            InternalAudioPlugin,
-            CradlePlugin,
-            InteractionPlugin,
-            MyScenePlugin,
-        ));
+        app.add_state::<GameState>()
+            .add_plugins((
+                LoadingPlugin,
+                ActionsPlugin,
+                MenuPlugin,
+                SettingsPlugin,
+                InternalAudioPlugin,
+                MyScenePlugin,
+                CameraControlPlugin,
+                SimulationPlugin,
+                HistoryPlugin,
+                ReplayPlugin,
+                EnvironmentPlugin,
+                LightingPlugin,
+                GraphicsPlugin,
+            ))
+            .add_plugins((
+                CradlePlugin,
+                InteractionPlugin,
+                LiftPlugin,
+                GamepadControlPlugin,
+                HammerPlugin,
+                LauncherPlugin,
+                ModelsPlugin,
+                BallMaterialsPlugin,
+                TrailsPlugin,
+                ContactsPlugin,
+                ForcesPlugin,
+            ));
 
         #[cfg(debug_assertions)]
         {
@@ -0,0 +1,122 @@
+use crate::{
+    actions::{Action, ActionState},
+    cradle::Ball,
+    history::RecordHistory,
+    GameState,
+};
+use bevy::prelude::*;
+use bevy_rapier3d::prelude::*;
+
+pub struct LiftPlugin;
+
+/// This plugin lets the keyboard lift single balls and drop all of them at the same instant,
+/// which is impossible to do by mouse
+impl Plugin for LiftPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(LiftSettings::default()).add_systems(
+            Update,
+            (
+                handle_lift_keys.run_if(in_state(GameState::Playing)),
+                move_lifted_balls.run_if(in_state(GameState::Playing)),
+                handle_release_key.run_if(in_state(GameState::Playing)),
+            )
+                .chain(),
+        );
+    }
+}
+
+#[derive(Resource)]
+pub struct LiftSettings {
+    /// angle from hanging straight down, in radians
+    pub angle: f32,
+    /// how fast a ball is raised, in radians per second
+    pub speed: f32,
+}
+
+impl Default for LiftSettings {
+    fn default() -> Self {
+        LiftSettings {
+            angle: 40f32.to_radians(),
+            speed: 2.0,
+        }
+    }
+}
+
+/// A ball held up until it is released, moved kinematically towards `angle`
+#[derive(Component, Clone, Copy, Debug)]
+pub struct Lifted {
+    pub angle: f32,
+}
+
+/// Balls on the left half of the row are raised to the left, the others to the right
+pub fn lift_direction(index: usize, ball_count: usize) -> f32 {
+    if index * 2 < ball_count {
+        -1.0
+    } else {
+        1.0
+    }
+}
+
+pub fn lift_ball(commands: &mut Commands, entity: Entity, angle: f32) {
+    commands
+        .entity(entity)
+        .insert((Lifted { angle }, RigidBody::KinematicPositionBased));
+}
+
+pub fn release_ball(commands: &mut Commands, entity: Entity) {
+    commands
+        .entity(entity)
+        .remove::<Lifted>()
+        .insert((RigidBody::Dynamic, Velocity::zero()));
+}
+
+fn handle_lift_keys(
+    actions: Res<ActionState>,
+    lift_settings: Res<LiftSettings>,
+    mut commands: Commands,
+    balls: Query<(Entity, &Ball), Without<Lifted>>,
+    all_balls: Query<&Ball>,
+    mut history: EventWriter<RecordHistory>,
+) {
+    let ball_count = all_balls.iter().count();
+    for (entity, ball) in &balls {
+        let Ok(action_index) = u8::try_from(ball.index) else {
+            continue;
+        };
+        if actions.just_pressed(Action::LiftBall(action_index)) {
+            let angle = lift_direction(ball.index, ball_count) * lift_settings.angle;
+            history.send(RecordHistory);
+            lift_ball(&mut commands, entity, angle);
+        }
+    }
+}
+
+fn move_lifted_balls(
+    time: Res<Time>,
+    lift_settings: Res<LiftSettings>,
+    mut balls: Query<(&Ball, &Lifted, &mut Transform)>,
+) {
+    for (ball, lifted, mut transform) in &mut balls {
+        // swing around the pivot, keeping the rope length the ball currently has
+        let offset = transform.translation - ball.pivot;
+        let radius = offset.truncate().length();
+        let current_angle = offset.x.atan2(-offset.y);
+        let max_step = lift_settings.speed * time.delta_seconds();
+        let angle = current_angle + (lifted.angle - current_angle).clamp(-max_step, max_step);
+
+        transform.translation.x = ball.pivot.x + radius * angle.sin();
+        transform.translation.y = ball.pivot.y - radius * angle.cos();
+    }
+}
+
+fn handle_release_key(
+    actions: Res<ActionState>,
+    mut commands: Commands,
+    lifted: Query<Entity, With<Lifted>>,
+) {
+    if actions.just_pressed(Action::Release) {
+        for entity in &lifted {
+            release_ball(&mut commands, entity);
+        }
+    }
+}
@@ -0,0 +1,310 @@
+use crate::{camera::MainCamera, cradle::cradle_center, GameState};
+use bevy::prelude::*;
+use std::f32::consts::TAU;
+
+pub struct LightingPlugin;
+
+/// This plugin lights the scene from a few presets and can move the sun through a day
+impl Plugin for LightingPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(LightingSettings::default())
+            .insert_resource(TimeOfDay::default())
+            .add_systems(OnEnter(GameState::Playing), setup_lights)
+            .add_systems(
+                Update,
+                (
+                    advance_time_of_day.run_if(in_state(GameState::Playing)),
+                    apply_lighting.run_if(in_state(GameState::Playing)),
+                )
+                    .chain(),
+            );
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum LightingPreset {
+    /// bright and even, no strong shadows from one side
+    Studio,
+    /// a low sun, the way the scene has always been lit
+    #[default]
+    Daylight,
+    Dusk,
+    /// a single spot from above in the dark
+    Spotlight,
+}
+
+impl LightingPreset {
+    pub const ALL: [LightingPreset; 4] = [
+        LightingPreset::Studio,
+        LightingPreset::Daylight,
+        LightingPreset::Dusk,
+        LightingPreset::Spotlight,
+    ];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            LightingPreset::Studio => "Studio",
+            LightingPreset::Daylight => "Daylight",
+            LightingPreset::Dusk => "Dusk",
+            LightingPreset::Spotlight => "Spotlight",
+        }
+    }
+
+    pub fn next(&self, direction: i32) -> LightingPreset {
+        let current = Self::ALL
+            .iter()
+            .position(|preset| preset == self)
+            .unwrap_or(0) as i32;
+        Self::ALL[(current + direction).rem_euclid(Self::ALL.len() as i32) as usize]
+    }
+
+    fn rig(&self) -> LightingRig {
+        match self {
+            LightingPreset::Studio => LightingRig {
+                sun_color: Color::WHITE,
+                sun_illuminance: 30_000.0,
+                sun_direction: Vec3::new(-0.3, -0.8, -0.5),
+                lamp_color: Color::WHITE,
+                lamp_intensity: 12_000.0,
+                spot_intensity: 0.0,
+                ambient_brightness: 0.3,
+                fog_color: Color::rgb(0.3, 0.3, 0.32),
+                fog_sun_color: Color::NONE,
+                fog_start: 80.0,
+                fog_end: 500.0,
+            },
+            LightingPreset::Daylight => LightingRig {
+                sun_color: Color::rgb(0.98, 0.95, 0.82),
+                sun_illuminance: 100_000.0,
+                sun_direction: Vec3::new(-0.15, -0.05, 0.25),
+                lamp_color: Color::WHITE,
+                lamp_intensity: 9_000.0,
+                spot_intensity: 0.0,
+                ambient_brightness: 0.05,
+                fog_color: Color::rgb(0.1, 0.2, 0.4),
+                fog_sun_color: Color::rgba(1.0, 0.95, 0.75, 0.5),
+                fog_start: 50.0,
+                fog_end: 400.0,
+            },
+            LightingPreset::Dusk => LightingRig {
+                sun_color: Color::rgb(1.0, 0.55, 0.3),
+                sun_illuminance: 15_000.0,
+                sun_direction: Vec3::new(0.8, -0.12, 0.2),
+                lamp_color: Color::rgb(1.0, 0.7, 0.45),
+                lamp_intensity: 4_000.0,
+                spot_intensity: 0.0,
+                ambient_brightness: 0.03,
+                fog_color: Color::rgb(0.35, 0.2, 0.25),
+                fog_sun_color: Color::rgba(1.0, 0.5, 0.2, 0.6),
+                fog_start: 30.0,
+                fog_end: 250.0,
+            },
+            LightingPreset::Spotlight => LightingRig {
+                sun_color: Color::rgb(0.6, 0.7, 1.0),
+                sun_illuminance: 500.0,
+                sun_direction: Vec3::new(0.0, -1.0, 0.3),
+                lamp_color: Color::WHITE,
+                lamp_intensity: 0.0,
+                spot_intensity: 60_000.0,
+                ambient_brightness: 0.01,
+                fog_color: Color::rgb(0.02, 0.02, 0.03),
+                fog_sun_color: Color::NONE,
+                fog_start: 20.0,
+                fog_end: 150.0,
+            },
+        }
+    }
+}
+
+#[derive(Resource, Clone, Debug, PartialEq)]
+pub struct LightingSettings {
+    pub preset: LightingPreset,
+    /// move the sun around the cradle instead of keeping the preset's sun
+    pub day_cycle: bool,
+    /// seconds for a whole day and night
+    pub day_length: f32,
+}
+
+impl Default for LightingSettings {
+    fn default() -> Self {
+        LightingSettings {
+            preset: LightingPreset::default(),
+            day_cycle: false,
+            day_length: 120.0,
+        }
+    }
+}
+
+/// Fraction of the day, 0.25 is sunrise, 0.5 noon and 0.75 sunset.
+/// Kept apart from the settings, so the settings panel does not rebuild every frame
+#[derive(Resource)]
+pub struct TimeOfDay(pub f32);
+
+impl Default for TimeOfDay {
+    fn default() -> Self {
+        TimeOfDay(0.35)
+    }
+}
+
+/// Everything a preset sets, the day cycle blends it with night
+#[derive(Clone, Copy, Debug)]
+struct LightingRig {
+    sun_color: Color,
+    sun_illuminance: f32,
+    /// the way the sunlight travels
+    sun_direction: Vec3,
+    lamp_color: Color,
+    lamp_intensity: f32,
+    spot_intensity: f32,
+    ambient_brightness: f32,
+    fog_color: Color,
+    fog_sun_color: Color,
+    fog_start: f32,
+    fog_end: f32,
+}
+
+impl LightingRig {
+    /// The preset with the sun moved to `time_of_day`
+    fn at_time_of_day(self, time_of_day: f32) -> LightingRig {
+        let angle = (time_of_day - 0.25) * TAU;
+        let sun_position = Vec3::new(angle.cos(), angle.sin(), 0.3).normalize();
+        let height = sun_position.y;
+        // how far the sun is above the horizon, 0 at night
+        let daylight = (height * 3.0).clamp(0.0, 1.0);
+        let twilight = 1.0 - (height.abs() * 4.0).clamp(0.0, 1.0);
+
+        let night_fog = Color::rgb(0.02, 0.03, 0.08);
+        let dusk_fog = Color::rgb(0.35, 0.2, 0.25);
+        let horizon_sun = Color::rgb(1.0, 0.5, 0.25);
+
+        LightingRig {
+            sun_color: mix(horizon_sun, self.sun_color, daylight),
+            sun_illuminance: self.sun_illuminance * daylight,
+            sun_direction: -sun_position,
+            ambient_brightness: self.ambient_brightness * daylight.max(0.2),
+            fog_color: mix(mix(night_fog, self.fog_color, daylight), dusk_fog, twilight),
+            fog_sun_color: mix(Color::NONE, horizon_sun.with_a(0.6), twilight),
+            ..self
+        }
+    }
+}
+
+fn mix(from: Color, to: Color, t: f32) -> Color {
+    let mixed = Vec4::from(from.as_rgba_f32()).lerp(Vec4::from(to.as_rgba_f32()), t);
+    Color::rgba(mixed.x, mixed.y, mixed.z, mixed.w)
+}
+
+#[derive(Component, Default)]
+struct Sun {}
+
+#[derive(Component, Default)]
+struct Lamp {}
+
+#[derive(Component, Default)]
+struct Spot {}
+
+fn setup_lights(mut commands: Commands) {
+    // directional 'sun' light
+    commands.spawn((
+        DirectionalLightBundle {
+            directional_light: DirectionalLight {
+                shadows_enabled: true,
+                ..default()
+            },
+            ..default()
+        },
+        Sun::default(),
+    ));
+
+    commands.spawn((
+        PointLightBundle {
+            point_light: PointLight {
+                range: 200.,
+                shadows_enabled: true,
+                ..default()
+            },
+            transform: Transform::from_xyz(8.0, 16.0, 8.0),
+            ..default()
+        },
+        Lamp::default(),
+    ));
+
+    let center = cradle_center();
+    commands.spawn((
+        SpotLightBundle {
+            spot_light: SpotLight {
+                range: 100.0,
+                inner_angle: 0.3,
+                outer_angle: 0.5,
+                shadows_enabled: true,
+                ..default()
+            },
+            transform: Transform::from_translation(center + Vec3::new(0.0, 25.0, 8.0))
+                .looking_at(center, Vec3::Y),
+            ..default()
+        },
+        Spot::default(),
+    ));
+}
+
+fn advance_time_of_day(
+    time: Res<Time>,
+    lighting_settings: Res<LightingSettings>,
+    mut time_of_day: ResMut<TimeOfDay>,
+) {
+    if lighting_settings.day_cycle {
+        time_of_day.0 = (time_of_day.0 + time.delta_seconds() / lighting_settings.day_length) % 1.0;
+    }
+}
+
+fn apply_lighting(
+    lighting_settings: Res<LightingSettings>,
+    time_of_day: Res<TimeOfDay>,
+    mut ambient_light: ResMut<AmbientLight>,
+    mut suns: Query<(&mut DirectionalLight, &mut Transform), With<Sun>>,
+    mut lamps: Query<(&mut PointLight, &mut Visibility), With<Lamp>>,
+    mut spots: Query<(&mut SpotLight, &mut Visibility), With<Spot>>,
+    mut fogs: Query<&mut FogSettings, With<MainCamera>>,
+    added: Query<(), Or<(Added<Sun>, (Added<FogSettings>, With<MainCamera>))>>,
+) {
+    if !(lighting_settings.is_changed() || time_of_day.is_changed() || !added.is_empty()) {
+        return;
+    }
+
+    let mut rig = lighting_settings.preset.rig();
+    if lighting_settings.day_cycle {
+        rig = rig.at_time_of_day(time_of_day.0);
+    }
+
+    for (mut sun, mut transform) in &mut suns {
+        sun.color = rig.sun_color;
+        sun.illuminance = rig.sun_illuminance;
+        *transform = Transform::default().looking_at(rig.sun_direction, Vec3::Y);
+    }
+    // a hidden light is not rendered at all, so an unused one costs no shadow map either
+    let shown = |intensity: f32| {
+        if intensity > 0.0 {
+            Visibility::Inherited
+        } else {
+            Visibility::Hidden
+        }
+    };
+    for (mut lamp, mut visibility) in &mut lamps {
+        lamp.color = rig.lamp_color;
+        lamp.intensity = rig.lamp_intensity;
+        visibility.set_if_neq(shown(rig.lamp_intensity));
+    }
+    for (mut spot, mut visibility) in &mut spots {
+        spot.intensity = rig.spot_intensity;
+        visibility.set_if_neq(shown(rig.spot_intensity));
+    }
+    ambient_light.brightness = rig.ambient_brightness;
+    for mut fog in &mut fogs {
+        fog.color = rig.fog_color;
+        fog.directional_light_color = rig.fog_sun_color;
+        fog.falloff = FogFalloff::Linear {
+            start: rig.fog_start,
+            end: rig.fog_end,
+        };
+    }
+}
@@ -13,7 +13,6 @@ use winit::window::Icon;
 
 fn main() {
     App::new()
-        .insert_resource(Msaa::Sample8)
         .insert_resource(ClearColor(Color::rgb(0.4, 0.4, 0.4)))
         .add_plugins(DefaultPlugins.set(WindowPlugin {
             primary_window: Some(Window {
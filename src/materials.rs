@@ -0,0 +1,232 @@
+use crate::{
+    config::{load_config, save_config},
+    cradle::Ball,
+    loading::TextureAssets,
+    GameState,
+};
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+pub struct BallMaterialsPlugin;
+
+const CRADLE_PRESET_CONFIG: &str = "cradle_preset";
+
+/// This plugin gives the balls the finish picked in the settings panel, for all of them
+/// or one by one, and keeps it with the cradle preset
+impl Plugin for BallMaterialsPlugin {
+    fn build(&self, app: &mut App) {
+        let cradle_preset = load_config::<CradlePreset>(CRADLE_PRESET_CONFIG).unwrap_or_default();
+        app.insert_resource(cradle_preset.finishes)
+            .insert_resource(EditedBall::default())
+            .add_systems(
+                Update,
+                (
+                    apply_ball_finishes.run_if(in_state(GameState::Playing)),
+                    save_ball_finishes,
+                ),
+            );
+    }
+}
+
+/// Colors to pick from in the panel, as there is no color picker
+pub const BALL_COLORS: [(&str, Color); 8] = [
+    ("White", Color::WHITE),
+    ("Steel", Color::rgb(0.56, 0.57, 0.58)),
+    ("Gold", Color::rgb(1.0, 0.77, 0.34)),
+    ("Copper", Color::rgb(0.95, 0.64, 0.54)),
+    ("Red", Color::rgb(0.8, 0.1, 0.1)),
+    ("Green", Color::rgb(0.1, 0.6, 0.2)),
+    ("Blue", Color::rgb(0.1, 0.3, 0.8)),
+    ("Black", Color::rgb(0.05, 0.05, 0.05)),
+];
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum BallTexture {
+    #[default]
+    None,
+    /// the bevy logo from [`TextureAssets::texture_bevy`]
+    Bevy,
+}
+
+impl BallTexture {
+    pub fn next(&self) -> BallTexture {
+        match self {
+            BallTexture::None => BallTexture::Bevy,
+            BallTexture::Bevy => BallTexture::None,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct BallFinish {
+    pub base_color: Color,
+    pub metallic: f32,
+    pub roughness: f32,
+    /// how strongly the ball glows in its own color
+    pub emissive: f32,
+    pub texture: BallTexture,
+}
+
+impl Default for BallFinish {
+    fn default() -> Self {
+        // polished steel, the balls have always looked like this
+        BallFinish {
+            base_color: Color::WHITE,
+            metallic: 1.0,
+            roughness: 0.1,
+            emissive: 0.0,
+            texture: BallTexture::None,
+        }
+    }
+}
+
+impl BallFinish {
+    pub fn color_name(&self) -> &'static str {
+        BALL_COLORS
+            .iter()
+            .find(|(_, color)| *color == self.base_color)
+            .map_or("Custom", |(name, _)| name)
+    }
+
+    pub fn next_color(&mut self, direction: i32) {
+        let current = BALL_COLORS
+            .iter()
+            .position(|(_, color)| *color == self.base_color)
+            .unwrap_or(0) as i32;
+        let next = (current + direction).rem_euclid(BALL_COLORS.len() as i32) as usize;
+        self.base_color = BALL_COLORS[next].1;
+    }
+
+    fn material(&self, texture_assets: Option<&TextureAssets>) -> StandardMaterial {
+        let texture = match self.texture {
+            BallTexture::None => None,
+            BallTexture::Bevy => texture_assets.map(|textures| textures.texture_bevy.clone()),
+        };
+        StandardMaterial {
+            base_color: self.base_color,
+            base_color_texture: texture,
+            metallic: self.metallic,
+            perceptual_roughness: self.roughness,
+            emissive: self.base_color * self.emissive,
+            ..default()
+        }
+    }
+}
+
+/// The finish of every ball, the ones changed one by one are kept apart
+#[derive(Resource, Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct BallFinishes {
+    pub all: BallFinish,
+    /// by [`Ball::index`]
+    pub balls: BTreeMap<usize, BallFinish>,
+}
+
+impl BallFinishes {
+    pub fn finish(&self, index: Option<usize>) -> &BallFinish {
+        index
+            .and_then(|index| self.balls.get(&index))
+            .unwrap_or(&self.all)
+    }
+
+    /// Changes one ball, or all of them and drops what was set one by one
+    pub fn edit(&mut self, index: Option<usize>, edit: impl FnOnce(&mut BallFinish)) {
+        match index {
+            Some(index) => {
+                let all = self.all.clone();
+                edit(self.balls.entry(index).or_insert(all));
+            }
+            None => {
+                edit(&mut self.all);
+                self.balls.clear();
+            }
+        }
+    }
+}
+
+/// What is stored for the cradle in `config/cradle_preset.ron`
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+struct CradlePreset {
+    finishes: BallFinishes,
+}
+
+/// How a ball part glows on its own, highlights are added on top of it
+#[derive(Component, Clone, Copy, Debug)]
+pub struct BallEmissive(pub Color);
+
+/// The ball the settings panel edits, all of them when `None`
+#[derive(Resource, Default)]
+pub struct EditedBall {
+    pub index: Option<usize>,
+}
+
+impl EditedBall {
+    /// Steps through all balls, then each ball
+    pub fn cycle(&mut self, direction: i32, ball_count: usize) {
+        let choices = ball_count as i32 + 1;
+        let current = self.index.map_or(0, |index| index as i32 + 1);
+        let next = (current + direction).rem_euclid(choices);
+        self.index = if next == 0 {
+            None
+        } else {
+            Some(next as usize - 1)
+        };
+    }
+}
+
+fn apply_ball_finishes(
+    mut commands: Commands,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    ball_finishes: Res<BallFinishes>,
+    texture_assets: Option<Res<TextureAssets>>,
+    balls: Query<(Entity, Ref<Ball>)>,
+    children: Query<&Children>,
+    mut ball_materials: Query<(Entity, &mut Handle<StandardMaterial>)>,
+) {
+    // the finish as it was loaded only replaces the materials once it was ever changed,
+    // until then a ball from a glTF model keeps the materials it came with
+    let edited = ball_finishes.is_changed() && !ball_finishes.is_added();
+    let finish_set = *ball_finishes != BallFinishes::default();
+    for (entity, ball) in &balls {
+        let added = ball.is_added();
+        if !(edited || (added && finish_set)) {
+            if added {
+                // remember the glow the ball came with
+                let mut ball_parts = ball_materials.iter_many_mut(
+                    std::iter::once(entity).chain(children.iter_descendants(entity)),
+                );
+                while let Some((part, handle)) = ball_parts.fetch_next() {
+                    if let Some(material) = materials.get(&*handle) {
+                        commands
+                            .entity(part)
+                            .insert(BallEmissive(material.emissive));
+                    }
+                }
+            }
+            continue;
+        }
+        let material = ball_finishes
+            .finish(Some(ball.index))
+            .material(texture_assets.as_deref());
+        let emissive = BallEmissive(material.emissive);
+        let material = materials.add(material);
+        // a ball from a glTF model has its meshes below it
+        let mut ball_parts = ball_materials
+            .iter_many_mut(std::iter::once(entity).chain(children.iter_descendants(entity)));
+        while let Some((part, mut handle)) = ball_parts.fetch_next() {
+            *handle = material.clone();
+            commands.entity(part).insert(emissive);
+        }
+    }
+}
+
+fn save_ball_finishes(ball_finishes: Res<BallFinishes>) {
+    if ball_finishes.is_changed() && !ball_finishes.is_added() {
+        save_config(
+            CRADLE_PRESET_CONFIG,
+            &CradlePreset {
+                finishes: ball_finishes.clone(),
+            },
+        );
+    }
+}
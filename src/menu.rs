@@ -1,24 +1,26 @@
 use crate::loading::FontAssets;
+use crate::settings::SettingsPanel;
 use crate::GameState;
 use bevy::prelude::*;
 
 pub struct MenuPlugin;
 
-/// This plugin is responsible for the game menu (containing only one button...)
+/// This plugin is responsible for the game menu (a play and a settings button)
 /// The menu is only drawn during the State `GameState::Menu` and is removed when that state is exited
 impl Plugin for MenuPlugin {
     fn build(&self, app: &mut App) {
         app.init_resource::<ButtonColors>()
             .add_systems(OnEnter(GameState::Menu), setup_menu)
-            .add_systems(Update, click_play_button.run_if(in_state(GameState::Menu)))
+            .add_systems(Update, click_menu_button.run_if(in_state(GameState::Menu)))
+            .add_systems(Update, update_button_colors)
             .add_systems(OnExit(GameState::Menu), cleanup_menu);
     }
 }
 
 #[derive(Resource)]
-struct ButtonColors {
-    normal: Color,
-    hovered: Color,
+pub struct ButtonColors {
+    pub normal: Color,
+    pub hovered: Color,
 }
 
 impl Default for ButtonColors {
@@ -30,39 +32,88 @@ impl Default for ButtonColors {
     }
 }
 
+#[derive(Component)]
+struct MenuRoot;
+
+#[derive(Component, Clone, Copy)]
+enum MenuButton {
+    Play,
+    Settings,
+}
+
 fn setup_menu(
     mut commands: Commands,
     font_assets: Res<FontAssets>,
     button_colors: Res<ButtonColors>,
 ) {
     commands
-        .spawn(ButtonBundle {
-            style: Style {
-                width: Val::Px(120.0),
-                height: Val::Px(50.0),
-                margin: UiRect::all(Val::Auto),
-                justify_content: JustifyContent::Center,
-                align_items: AlignItems::Center,
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    width: Val::Percent(100.0),
+                    height: Val::Percent(100.0),
+                    flex_direction: FlexDirection::Column,
+                    justify_content: JustifyContent::Center,
+                    align_items: AlignItems::Center,
+                    ..Default::default()
+                },
                 ..Default::default()
             },
-            background_color: button_colors.normal.into(),
-            ..Default::default()
-        })
+            MenuRoot,
+        ))
         .with_children(|parent| {
-            parent.spawn(TextBundle::from_section(
-                "Play",
-                TextStyle {
-                    font: font_assets.fira_sans.clone(),
-                    font_size: 40.0,
-                    color: Color::rgb(0.9, 0.9, 0.9),
-                },
-            ));
+            for (button, label) in [
+                (MenuButton::Play, "Play"),
+                (MenuButton::Settings, "Settings"),
+            ] {
+                parent
+                    .spawn((
+                        ButtonBundle {
+                            style: Style {
+                                width: Val::Px(160.0),
+                                height: Val::Px(50.0),
+                                margin: UiRect::all(Val::Px(8.0)),
+                                justify_content: JustifyContent::Center,
+                                align_items: AlignItems::Center,
+                                ..Default::default()
+                            },
+                            background_color: button_colors.normal.into(),
+                            ..Default::default()
+                        },
+                        button,
+                    ))
+                    .with_children(|parent| {
+                        parent.spawn(TextBundle::from_section(
+                            label,
+                            TextStyle {
+                                font: font_assets.fira_sans.clone(),
+                                font_size: 40.0,
+                                color: Color::rgb(0.9, 0.9, 0.9),
+                            },
+                        ));
+                    });
+            }
         });
 }
 
-fn click_play_button(
-    button_colors: Res<ButtonColors>,
+fn click_menu_button(
     mut state: ResMut<NextState<GameState>>,
+    mut settings_panel: ResMut<SettingsPanel>,
+    interaction_query: Query<(&Interaction, &MenuButton), Changed<Interaction>>,
+) {
+    for (interaction, button) in &interaction_query {
+        if *interaction == Interaction::Pressed {
+            match button {
+                MenuButton::Play => state.set(GameState::Playing),
+                MenuButton::Settings => settings_panel.open = true,
+            }
+        }
+    }
+}
+
+/// Hover feedback for every button, in the menu as well as in the settings panel
+fn update_button_colors(
+    button_colors: Res<ButtonColors>,
     mut interaction_query: Query<
         (&Interaction, &mut BackgroundColor),
         (Changed<Interaction>, With<Button>),
@@ -70,10 +121,7 @@ fn click_play_button(
 ) {
     for (interaction, mut color) in &mut interaction_query {
         match *interaction {
-            Interaction::Pressed => {
-                state.set(GameState::Playing);
-            }
-            Interaction::Hovered => {
+            Interaction::Pressed | Interaction::Hovered => {
                 *color = button_colors.hovered.into();
             }
             Interaction::None => {
@@ -83,6 +131,6 @@ fn click_play_button(
     }
 }
 
-fn cleanup_menu(mut commands: Commands, button: Query<Entity, With<Button>>) {
-    commands.entity(button.single()).despawn_recursive();
+fn cleanup_menu(mut commands: Commands, menu: Query<Entity, With<MenuRoot>>) {
+    commands.entity(menu.single()).despawn_recursive();
 }
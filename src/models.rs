@@ -0,0 +1,399 @@
+use crate::{
+    collision::{anchor_groups, ground_groups},
+    config::load_config,
+    cradle::{ball_physics, Ball, CradlePart, RopeJoint},
+    GameState,
+};
+use bevy::{gltf::GltfExtras, prelude::*, render::primitives::Aabb, scene::SceneInstance};
+use bevy_rapier3d::prelude::*;
+use serde::{Deserialize, Serialize};
+
+pub struct ModelsPlugin;
+
+const MODELS_CONFIG: &str = "models";
+
+/// This plugin puts the table and the cradle frame from glTF files into the scene
+/// and gives them colliders made from their meshes, a whole cradle can come from one too
+impl Plugin for ModelsPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(load_config::<SceneModels>(MODELS_CONFIG).unwrap_or_default())
+            .add_systems(OnEnter(GameState::Playing), spawn_models)
+            .add_systems(
+                Update,
+                (
+                    add_model_colliders.run_if(in_state(GameState::Playing)),
+                    build_cradle_models.run_if(in_state(GameState::Playing)),
+                ),
+            );
+    }
+}
+
+/// How the colliders of a model are made from its meshes
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub enum ModelCollider {
+    /// the exact triangles, fine for anything that does not move
+    #[default]
+    TriMesh,
+    /// one convex hull around every mesh
+    ConvexHull,
+    /// only visual, nothing collides with it
+    None,
+}
+
+/// A glTF file placed in the scene, the path is relative to the assets folder.
+/// Nodes named `collider*` are used as the colliders and hidden,
+/// without them every mesh of the model collides
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ModelSource {
+    pub path: String,
+    /// the scene of the file to show
+    pub scene: usize,
+    pub translation: Vec3,
+    pub rotation: Quat,
+    pub scale: f32,
+    pub collider: ModelCollider,
+}
+
+impl ModelSource {
+    fn transform(&self) -> Transform {
+        Transform {
+            translation: self.translation,
+            rotation: self.rotation,
+            scale: Vec3::splat(self.scale),
+        }
+    }
+}
+
+/// Models replacing the default scenery, set in `config/models.ron`, e.g.
+/// `(table: Some((path: "models/desk.glb", scene: 0, translation: (10.0, -15.0, 0.0),
+/// rotation: (0.0, 0.0, 0.0, 1.0), scale: 1.0, collider: TriMesh)), frame: None)`
+#[derive(Resource, Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct SceneModels {
+    /// the ground or desk under the cradle, the plain ground plane is used without it
+    pub table: Option<ModelSource>,
+    /// posts and bars holding the anchors, only for looks unless its colliders are hit
+    pub frame: Option<ModelSource>,
+    /// a whole cradle taking the place of the built in one, see [`spawn_cradle_model`]
+    pub cradle: Option<ModelSource>,
+}
+
+/// A model whose scene is still being spawned, colliders are added once it is there
+#[derive(Component)]
+struct PendingColliders {
+    collider: ModelCollider,
+}
+
+fn spawn_models(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    scene_models: Res<SceneModels>,
+) {
+    for source in [&scene_models.table, &scene_models.frame]
+        .into_iter()
+        .flatten()
+    {
+        info!("loading model {}", source.path);
+        commands.spawn((
+            SceneBundle {
+                scene: asset_server.load(format!("{}#Scene{}", source.path, source.scene)),
+                transform: source.transform(),
+                ..default()
+            },
+            RigidBody::Fixed,
+            PendingColliders {
+                collider: source.collider,
+            },
+        ));
+    }
+}
+
+fn add_model_colliders(
+    mut commands: Commands,
+    scene_spawner: Res<SceneSpawner>,
+    meshes: Res<Assets<Mesh>>,
+    models: Query<(Entity, &SceneInstance, &PendingColliders)>,
+    children: Query<&Children>,
+    parents: Query<&Parent>,
+    names: Query<&Name>,
+    mesh_handles: Query<&Handle<Mesh>>,
+) {
+    for (model, instance, pending) in &models {
+        if !scene_spawner.instance_is_ready(**instance) {
+            continue;
+        }
+        commands.entity(model).remove::<PendingColliders>();
+        if pending.collider == ModelCollider::None {
+            continue;
+        }
+
+        // glTF puts the node name on the node and the mesh on a child of it
+        let is_collider_node = |entity: Entity| {
+            let own = names.get(entity).ok();
+            let parent = parents
+                .get(entity)
+                .ok()
+                .and_then(|parent| names.get(parent.get()).ok());
+            [own, parent]
+                .into_iter()
+                .flatten()
+                .any(|name| name.as_str().to_lowercase().starts_with("collider"))
+        };
+        let mesh_entities: Vec<Entity> = children
+            .iter_descendants(model)
+            .filter(|entity| mesh_handles.contains(*entity))
+            .collect();
+        let named: Vec<Entity> = mesh_entities
+            .iter()
+            .copied()
+            .filter(|entity| is_collider_node(*entity))
+            .collect();
+        let collider_entities = if named.is_empty() {
+            mesh_entities
+        } else {
+            named
+        };
+
+        let shape = match pending.collider {
+            ModelCollider::ConvexHull => ComputedColliderShape::ConvexHull,
+            _ => ComputedColliderShape::TriMesh,
+        };
+        for entity in collider_entities {
+            let Some(mesh) = mesh_handles
+                .get(entity)
+                .ok()
+                .and_then(|handle| meshes.get(handle))
+            else {
+                continue;
+            };
+            let Some(collider) = Collider::from_bevy_mesh(mesh, &shape) else {
+                warn!("a mesh of a model has no triangles to collide with");
+                continue;
+            };
+            let mut entity_commands = commands.entity(entity);
+            entity_commands.insert((collider, ground_groups()));
+            if is_collider_node(entity) {
+                entity_commands.insert(Visibility::Hidden);
+            }
+        }
+    }
+}
+
+/// A cradle model whose scene is still being spawned
+#[derive(Component, Default)]
+struct PendingCradle {}
+
+/// Spawns a cradle described by a glTF scene. Nodes named `ball_<group>` become balls
+/// and nodes named `anchor_<group>` or `anchor_<group>_<anything>` hold the ball
+/// of the same group with ropes as long as the distance between them.
+/// Extras like `{"cradle": "ball", "group": 3, "radius": 1.0}` can be used instead of names.
+/// Everything else in the scene is only for looks
+pub fn spawn_cradle_model(
+    commands: &mut Commands,
+    asset_server: &AssetServer,
+    source: &ModelSource,
+) {
+    info!("loading cradle model {}", source.path);
+    commands.spawn((
+        SceneBundle {
+            scene: asset_server.load(format!("{}#Scene{}", source.path, source.scene)),
+            transform: source.transform(),
+            ..default()
+        },
+        PendingCradle::default(),
+        CradlePart::default(),
+    ));
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum CradleRole {
+    Ball,
+    Anchor,
+}
+
+/// What a node of a cradle model stands for
+#[derive(Clone, Debug)]
+struct CradleNode {
+    role: CradleRole,
+    /// balls are tied to the anchors of their group
+    group: String,
+    radius: Option<f32>,
+}
+
+#[derive(Deserialize)]
+struct CradleExtras {
+    cradle: Option<String>,
+    group: Option<serde_json::Value>,
+    radius: Option<f32>,
+}
+
+impl CradleNode {
+    fn from_gltf(name: Option<&Name>, extras: Option<&GltfExtras>) -> Option<CradleNode> {
+        let named = name.and_then(|name| CradleNode::from_name(name.as_str()));
+        let Some(extras) =
+            extras.and_then(|extras| serde_json::from_str::<CradleExtras>(&extras.value).ok())
+        else {
+            return named;
+        };
+        let role = match extras.cradle.as_deref().map(str::to_lowercase).as_deref() {
+            Some("ball") => CradleRole::Ball,
+            Some("anchor") => CradleRole::Anchor,
+            _ => return named,
+        };
+        let group = match extras.group {
+            Some(serde_json::Value::String(group)) => group,
+            Some(group) => group.to_string(),
+            None => named
+                .as_ref()
+                .map(|node| node.group.clone())
+                .or_else(|| name.map(|name| name.to_string()))
+                .unwrap_or_default(),
+        };
+        Some(CradleNode {
+            role,
+            group,
+            radius: extras.radius,
+        })
+    }
+
+    fn from_name(name: &str) -> Option<CradleNode> {
+        let name = name.to_lowercase();
+        let (role, rest) = if let Some(rest) = name.strip_prefix("ball_") {
+            (CradleRole::Ball, rest)
+        } else if let Some(rest) = name.strip_prefix("anchor_") {
+            (CradleRole::Anchor, rest)
+        } else {
+            return None;
+        };
+        // Blender adds `.001` to copies
+        let group = rest.split(['_', '.']).next().unwrap_or_default();
+        Some(CradleNode {
+            role,
+            group: group.to_string(),
+            radius: None,
+        })
+    }
+}
+
+/// Turns the nodes of a spawned cradle model into anchors and balls on ropes
+fn build_cradle_models(
+    mut commands: Commands,
+    scene_spawner: Res<SceneSpawner>,
+    models: Query<(Entity, &SceneInstance), With<PendingCradle>>,
+    children: Query<&Children>,
+    nodes: Query<(&GlobalTransform, Option<&Name>, Option<&GltfExtras>), Without<Handle<Mesh>>>,
+    bounds: Query<&Aabb>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    mut part_materials: Query<&mut Handle<StandardMaterial>>,
+) {
+    for (model, instance) in &models {
+        if !scene_spawner.instance_is_ready(**instance) {
+            continue;
+        }
+        commands.entity(model).remove::<PendingCradle>();
+
+        let mut balls = Vec::new();
+        let mut anchors = Vec::new();
+        for entity in children.iter_descendants(model) {
+            let Ok((global_transform, name, extras)) = nodes.get(entity) else {
+                continue;
+            };
+            let Some(node) = CradleNode::from_gltf(name, extras) else {
+                continue;
+            };
+            let transform = global_transform.compute_transform();
+            match node.role {
+                CradleRole::Ball => {
+                    // the meshes hang below the node, their bounds give the size
+                    let radius = node.radius.unwrap_or_else(|| {
+                        children
+                            .iter_descendants(entity)
+                            .filter_map(|child| bounds.get(child).ok())
+                            .map(|aabb| aabb.half_extents.max_element())
+                            .fold(0.0, f32::max)
+                    });
+                    let radius = if radius > 0.0 { radius } else { 1.0 };
+                    balls.push((entity, transform, node.group, radius));
+                }
+                CradleRole::Anchor => anchors.push((entity, transform, node.group)),
+            }
+        }
+        if balls.is_empty() {
+            warn!("the cradle model has no ball_* nodes");
+        }
+
+        for (entity, transform, _) in &anchors {
+            commands.entity(*entity).remove_parent().insert((
+                *transform,
+                RigidBody::Fixed,
+                anchor_groups(),
+                CradlePart::default(),
+            ));
+        }
+
+        // numbered from the left end of the row, like the built in cradle
+        balls.sort_by(|a, b| a.1.translation.x.total_cmp(&b.1.translation.x));
+        for (index, (entity, transform, group, radius)) in balls.into_iter().enumerate() {
+            let position = transform.translation;
+            let ball_anchors: Vec<_> = anchors
+                .iter()
+                .filter(|(_, _, anchor_group)| *anchor_group == group)
+                .map(|(anchor, anchor_transform, _)| (*anchor, anchor_transform.translation))
+                .collect();
+            if ball_anchors.is_empty() {
+                warn!("ball_{group} of the cradle model has no anchor_{group}");
+            }
+
+            let pivot = match ball_anchors.as_slice() {
+                [(_, first), (_, second)] => {
+                    let line = (*second - *first).normalize_or_zero();
+                    *first + line * (position - *first).dot(line)
+                }
+                [] => position,
+                _ => {
+                    ball_anchors.iter().map(|(_, anchor)| *anchor).sum::<Vec3>()
+                        / ball_anchors.len() as f32
+                }
+            };
+
+            // glTF nodes share their materials, every ball gets copies it can tint on its own
+            for part in std::iter::once(entity).chain(children.iter_descendants(entity)) {
+                let Ok(mut handle) = part_materials.get_mut(part) else {
+                    continue;
+                };
+                if let Some(material) = materials.get(&*handle).cloned() {
+                    *handle = materials.add(material);
+                }
+            }
+
+            // ropes end on the surface of the ball, facing their anchor
+            let world_radius = radius * transform.scale.max_element();
+            let joints: Vec<_> = ball_anchors
+                .iter()
+                .map(|(anchor, anchor_position)| {
+                    let direction = (*anchor_position - position).normalize_or_zero();
+                    let length = (anchor_position.distance(position) - world_radius).max(0.0);
+                    let rope = RopeJointBuilder::new()
+                        .local_anchor2(transform.rotation.inverse() * direction * world_radius)
+                        .limits([0.0, length]);
+                    ImpulseJoint::new(*anchor, rope)
+                })
+                .collect();
+
+            commands
+                .entity(entity)
+                .remove_parent()
+                .insert((
+                    transform,
+                    ball_physics(radius),
+                    Ball { index, pivot },
+                    CradlePart::default(),
+                ))
+                .with_children(|parent| {
+                    // one child per joint, like the built in cradle
+                    for joint in joints {
+                        parent.spawn((joint, RopeJoint::default()));
+                    }
+                });
+        }
+    }
+}
@@ -0,0 +1,408 @@
+use std::collections::VecDeque;
+
+use crate::{
+    actions::{Action, ActionState},
+    audio::{BallImpact, BallSound},
+    GameState,
+};
+use bevy::{
+    core_pipeline::clear_color::ClearColorConfig,
+    prelude::*,
+    render::{camera::Viewport, view::RenderLayers},
+    utils::HashMap,
+    window::PrimaryWindow,
+};
+
+pub struct ReplayPlugin;
+
+/// This plugin records the last few seconds of the cradle and plays the latest
+/// hard impact back in slow motion, in an inset in the corner of the window
+impl Plugin for ReplayPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(ReplaySettings::default())
+            .insert_resource(ReplayRecorder::default())
+            .insert_resource(ReplayClip::default())
+            .add_systems(OnEnter(GameState::Playing), setup_replay_camera)
+            .add_systems(
+                Update,
+                (
+                    handle_replay_actions.run_if(in_state(GameState::Playing)),
+                    record_frame.run_if(in_state(GameState::Playing)),
+                    capture_impact.run_if(in_state(GameState::Playing)),
+                    play_clip.run_if(in_state(GameState::Playing)),
+                    update_replay_camera.run_if(in_state(GameState::Playing)),
+                )
+                    .chain(),
+            );
+    }
+}
+
+/// Ghosts of the replayed bodies live on this layer, only the replay camera sees it
+const REPLAY_LAYER: u8 = 1;
+
+#[derive(Resource)]
+pub struct ReplaySettings {
+    pub visible: bool,
+    pub paused: bool,
+    /// playback speed, 1.0 is real time
+    pub speed: f32,
+    /// seconds kept before and after the impact
+    pub before: f32,
+    pub after: f32,
+    /// collisions slower than this are not replayed
+    pub min_impact_speed: f32,
+    pub view_distance: f32,
+    /// fraction of the window width taken by the inset
+    pub inset_width: f32,
+}
+
+impl Default for ReplaySettings {
+    fn default() -> Self {
+        ReplaySettings {
+            visible: true,
+            paused: false,
+            speed: 0.25,
+            before: 0.4,
+            after: 0.8,
+            min_impact_speed: 4.0,
+            view_distance: 10.0,
+            inset_width: 0.3,
+        }
+    }
+}
+
+#[derive(Clone)]
+struct ReplayFrame {
+    time: f32,
+    bodies: Vec<(Entity, Transform)>,
+}
+
+/// The recent past of every sounding body, long enough to cut a clip from
+#[derive(Resource, Default)]
+struct ReplayRecorder {
+    frames: VecDeque<ReplayFrame>,
+    /// time and position of an impact waiting for its aftermath to be recorded
+    pending: Option<(f32, Vec3)>,
+}
+
+/// The impact being played back
+#[derive(Resource, Default)]
+struct ReplayClip {
+    /// times start at zero
+    frames: Vec<ReplayFrame>,
+    focus: Vec3,
+    playhead: f32,
+    /// a new impact only replaces the clip once it has been watched
+    played_through: bool,
+}
+
+impl ReplayClip {
+    fn duration(&self) -> f32 {
+        self.frames.last().map_or(0.0, |frame| frame.time)
+    }
+
+    /// Interpolated transform of `entity` at `time`
+    fn sample(&self, entity: Entity, time: f32) -> Option<Transform> {
+        let next_index = self.frames.iter().position(|frame| frame.time >= time)?;
+        let next = &self.frames[next_index];
+        let next_transform = find_body(next, entity)?;
+        let Some(previous) = next_index.checked_sub(1).map(|index| &self.frames[index]) else {
+            return Some(next_transform);
+        };
+        let Some(previous_transform) = find_body(previous, entity) else {
+            return Some(next_transform);
+        };
+
+        let span = next.time - previous.time;
+        let t = if span > f32::EPSILON {
+            (time - previous.time) / span
+        } else {
+            1.0
+        };
+        Some(Transform {
+            translation: previous_transform
+                .translation
+                .lerp(next_transform.translation, t),
+            rotation: previous_transform
+                .rotation
+                .slerp(next_transform.rotation, t),
+            scale: next_transform.scale,
+        })
+    }
+}
+
+fn find_body(frame: &ReplayFrame, entity: Entity) -> Option<Transform> {
+    frame
+        .bodies
+        .iter()
+        .find(|(body, _)| *body == entity)
+        .map(|(_, transform)| *transform)
+}
+
+/// Stands in for a recorded body in the inset
+#[derive(Component)]
+struct ReplayGhost {
+    source: Entity,
+}
+
+/// The camera of the inset, lit by the same environment as the main camera
+#[derive(Component, Default)]
+pub struct ReplayCamera {}
+
+/// How far behind the ghosts the backdrop of the inset hangs
+const BACKDROP_DISTANCE: f32 = 200.0;
+
+fn setup_replay_camera(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+) {
+    commands
+        .spawn((
+            Camera3dBundle {
+                camera: Camera {
+                    // drawn after the main camera, on top of it
+                    order: 1,
+                    is_active: false,
+                    ..default()
+                },
+                camera_3d: Camera3d {
+                    // clearing would wipe the whole window, not only the inset,
+                    // the backdrop below covers the scene instead
+                    clear_color: ClearColorConfig::None,
+                    ..default()
+                },
+                ..default()
+            },
+            VisibilityBundle::default(),
+            RenderLayers::layer(REPLAY_LAYER),
+            UiCameraConfig { show_ui: false },
+            ReplayCamera::default(),
+        ))
+        .with_children(|parent| {
+            // wide enough to fill the view at any aspect ratio
+            let size = 4.0 * BACKDROP_DISTANCE;
+            parent.spawn((
+                PbrBundle {
+                    mesh: meshes.add(shape::Quad::new(Vec2::splat(size)).into()),
+                    material: materials.add(StandardMaterial {
+                        base_color: Color::rgb(0.1, 0.1, 0.12),
+                        unlit: true,
+                        ..default()
+                    }),
+                    transform: Transform::from_xyz(0.0, 0.0, -BACKDROP_DISTANCE),
+                    ..default()
+                },
+                RenderLayers::layer(REPLAY_LAYER),
+            ));
+        });
+}
+
+fn handle_replay_actions(actions: Res<ActionState>, mut replay_settings: ResMut<ReplaySettings>) {
+    if actions.just_pressed(Action::ToggleReplay) {
+        replay_settings.visible = !replay_settings.visible;
+    }
+    if actions.just_pressed(Action::ReplayPause) {
+        replay_settings.paused = !replay_settings.paused;
+    }
+    if actions.just_pressed(Action::ReplaySlower) {
+        replay_settings.speed = (replay_settings.speed / 2.0).max(1.0 / 32.0);
+        info!("replay speed: {}", replay_settings.speed);
+    }
+    if actions.just_pressed(Action::ReplayFaster) {
+        replay_settings.speed = (replay_settings.speed * 2.0).min(1.0);
+        info!("replay speed: {}", replay_settings.speed);
+    }
+}
+
+fn record_frame(
+    time: Res<Time>,
+    replay_settings: Res<ReplaySettings>,
+    mut recorder: ResMut<ReplayRecorder>,
+    bodies: Query<(Entity, &Transform), With<BallSound>>,
+) {
+    let now = time.elapsed_seconds();
+    recorder.frames.push_back(ReplayFrame {
+        time: now,
+        bodies: bodies
+            .iter()
+            .map(|(entity, transform)| (entity, *transform))
+            .collect(),
+    });
+
+    let keep = replay_settings.before + replay_settings.after;
+    while let Some(frame) = recorder.frames.front() {
+        if now - frame.time > keep {
+            recorder.frames.pop_front();
+        } else {
+            break;
+        }
+    }
+}
+
+fn capture_impact(
+    mut commands: Commands,
+    time: Res<Time>,
+    replay_settings: Res<ReplaySettings>,
+    mut impacts: EventReader<BallImpact>,
+    mut recorder: ResMut<ReplayRecorder>,
+    mut clip: ResMut<ReplayClip>,
+    bodies: Query<&GlobalTransform, With<BallSound>>,
+    children: Query<&Children>,
+    parts: Query<(&Handle<Mesh>, &Handle<StandardMaterial>, &GlobalTransform)>,
+    ghosts: Query<Entity, With<ReplayGhost>>,
+) {
+    let now = time.elapsed_seconds();
+    let hardest = impacts
+        .iter()
+        .filter(|impact| impact.speed >= replay_settings.min_impact_speed)
+        .max_by(|a, b| a.speed.total_cmp(&b.speed))
+        .copied();
+
+    let ready_for_next = clip.frames.is_empty() || clip.played_through;
+    if let Some(impact) = hardest.filter(|_| recorder.pending.is_none() && ready_for_next) {
+        recorder.pending = Some((now, impact.position));
+    }
+
+    let Some((impact_time, focus)) = recorder.pending else {
+        return;
+    };
+    if now - impact_time < replay_settings.after {
+        return;
+    }
+    recorder.pending = None;
+
+    let start = impact_time - replay_settings.before;
+    let frames: Vec<_> = recorder
+        .frames
+        .iter()
+        .filter(|frame| frame.time >= start)
+        .map(|frame| ReplayFrame {
+            time: frame.time - start,
+            bodies: frame.bodies.clone(),
+        })
+        .collect();
+
+    for ghost in &ghosts {
+        commands.entity(ghost).despawn_recursive();
+    }
+
+    // one ghost per body, looking the same as the body it replays
+    let mut sources = HashMap::new();
+    for (entity, transform) in frames.iter().flat_map(|frame| &frame.bodies) {
+        sources.entry(*entity).or_insert(*transform);
+    }
+    for (source, transform) in sources {
+        let Ok(body_transform) = bodies.get(source) else {
+            continue;
+        };
+        commands
+            .spawn((
+                SpatialBundle {
+                    transform,
+                    ..default()
+                },
+                ReplayGhost { source },
+            ))
+            .with_children(|parent| {
+                // a ball from a glTF model has its meshes below it
+                let body_parts = parts
+                    .iter_many(std::iter::once(source).chain(children.iter_descendants(source)));
+                for (mesh, material, part_transform) in body_parts {
+                    parent.spawn((
+                        PbrBundle {
+                            mesh: mesh.clone(),
+                            material: material.clone(),
+                            transform: part_transform.reparented_to(body_transform),
+                            ..default()
+                        },
+                        RenderLayers::layer(REPLAY_LAYER),
+                    ));
+                }
+            });
+    }
+
+    *clip = ReplayClip {
+        frames,
+        focus,
+        playhead: 0.0,
+        played_through: false,
+    };
+}
+
+fn play_clip(
+    time: Res<Time>,
+    replay_settings: Res<ReplaySettings>,
+    mut clip: ResMut<ReplayClip>,
+    mut ghosts: Query<(&ReplayGhost, &mut Transform, &mut Visibility)>,
+) {
+    let duration = clip.duration();
+    if duration <= 0.0 {
+        return;
+    }
+
+    if !replay_settings.paused {
+        clip.playhead += time.delta_seconds() * replay_settings.speed;
+        if clip.playhead > duration {
+            clip.playhead %= duration;
+            clip.played_through = true;
+        }
+    }
+
+    for (ghost, mut transform, mut visibility) in &mut ghosts {
+        match clip.sample(ghost.source, clip.playhead) {
+            Some(sampled) => {
+                *transform = sampled;
+                *visibility = Visibility::Inherited;
+            }
+            None => *visibility = Visibility::Hidden,
+        }
+    }
+}
+
+fn update_replay_camera(
+    replay_settings: Res<ReplaySettings>,
+    clip: Res<ReplayClip>,
+    windows: Query<&Window, With<PrimaryWindow>>,
+    mut cameras: Query<(&mut Camera, &mut Transform), With<ReplayCamera>>,
+) {
+    let Ok(window) = windows.get_single() else {
+        return;
+    };
+    let margin = 10;
+    let width = (window.physical_width() as f32 * replay_settings.inset_width) as u32;
+    let height = width * 3 / 4;
+    let fits = width > 0 && height + 2 * margin < window.physical_height();
+
+    for (mut camera, mut transform) in &mut cameras {
+        let active = replay_settings.visible && fits && !clip.frames.is_empty();
+        if camera.is_active != active {
+            camera.is_active = active;
+        }
+        if !active {
+            continue;
+        }
+
+        // bottom left corner
+        let position = UVec2::new(margin, window.physical_height() - height - margin);
+        let size = UVec2::new(width, height);
+        let current = camera
+            .viewport
+            .as_ref()
+            .map(|viewport| (viewport.physical_position, viewport.physical_size));
+        if current != Some((position, size)) {
+            camera.viewport = Some(Viewport {
+                physical_position: position,
+                physical_size: size,
+                depth: 0.0..1.0,
+            });
+        }
+
+        let eye =
+            clip.focus + Vec3::new(0.0, 0.15, 1.0).normalize() * replay_settings.view_distance;
+        let target = Transform::from_translation(eye).looking_at(clip.focus, Vec3::Y);
+        if *transform != target {
+            *transform = target;
+        }
+    }
+}
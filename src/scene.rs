@@ -1,9 +1,12 @@
-use crate::{loading::TextureAssets, GameState};
-use bevy::{
-    core_pipeline::Skybox,
-    prelude::*,
-    render::render_resource::{TextureViewDescriptor, TextureViewDimension},
+use crate::{
+    camera::{MainCamera, OrbitCamera},
+    collision::ground_groups,
+    cradle::cradle_center,
+    models::SceneModels,
+    GameState,
 };
+use bevy::prelude::*;
+use bevy_rapier3d::prelude::*;
 pub struct MyScenePlugin;
 
 impl Plugin for MyScenePlugin {
@@ -13,30 +16,17 @@ impl Plugin for MyScenePlugin {
     }
 }
 
-fn setup_camera(
-    mut commands: Commands,
-    textures: Res<TextureAssets>,
-    mut images: ResMut<Assets<Image>>,
-) {
-    let image = images.get_mut(&textures.skybox_cubemap).unwrap();
-    // NOTE: PNGs do not have any metadata that could indicate they contain a cubemap texture,
-    // so they appear as one texture. The following code reconfigures the texture as necessary.
-    if image.texture_descriptor.array_layer_count() == 1 {
-        image.reinterpret_stacked_2d_as_array(
-            image.texture_descriptor.size.height / image.texture_descriptor.size.width,
-        );
-        image.texture_view_descriptor = Some(TextureViewDescriptor {
-            dimension: Some(TextureViewDimension::Cube),
-            ..default()
-        });
-    };
-
+/// The sky and the environment lighting are added by the environment plugin
+fn setup_camera(mut commands: Commands) {
+    let eye = Vec3::new(15.0, 5.0, 42.0);
+    let focus = cradle_center();
     commands.spawn((
         Camera3dBundle {
-            transform: Transform::from_xyz(15.0, 5.0, 42.0)
-                .looking_at(Vec3::new(13.0, 1.0, 1.0), Vec3::Y),
+            transform: Transform::from_translation(eye).looking_at(focus, Vec3::Y),
             ..Default::default()
         },
+        OrbitCamera::looking_at(eye, focus),
+        MainCamera::default(),
         FogSettings {
             color: Color::rgba(0.1, 0.2, 0.4, 1.0),
             directional_light_color: Color::rgba(1.0, 0.95, 0.75, 0.5),
@@ -51,58 +41,36 @@ fn setup_camera(
                 Color::rgb(0.8, 0.844, 1.0), // atmospheric inscattering color (light gained due to scattering from the sun)
             ),  */
         },
-        EnvironmentMapLight {
-            diffuse_map: textures.skybox_cubemap.clone(),
-            specular_map: textures.skybox_cubemap.clone(),
-        },
-        Skybox(textures.skybox_cubemap.clone()),
     ));
 }
 
+pub const GROUND_HEIGHT: f32 = -15.0;
+
 fn setup(
     mut commands: Commands,
     mut meshes: ResMut<Assets<Mesh>>,
     mut materials: ResMut<Assets<StandardMaterial>>,
+    scene_models: Res<SceneModels>,
 ) {
-    // plane
-    commands.spawn(PbrBundle {
-        mesh: meshes.add(shape::Plane::from_size(200.0).into()),
-        material: materials.add(StandardMaterial {
-            base_color: Color::SILVER,
-            perceptual_roughness: 1.0,
-            ..default()
-        }),
-        transform: Transform::from_xyz(10.0, -15.0, 0.0),
-        ..default()
-    });
-
-    // directional 'sun' light
-    commands.spawn(DirectionalLightBundle {
-        directional_light: DirectionalLight {
-            color: Color::rgb(0.98, 0.95, 0.82),
-            shadows_enabled: true,
-            ..default()
-        },
-        transform: Transform::from_xyz(0.0, 0.0, 0.0)
-            .looking_at(Vec3::new(-0.15, -0.05, 0.25), Vec3::Y),
-        /*         cascade_shadow_config: CascadeShadowConfigBuilder {
-            num_cascades: 2,
-            first_cascade_far_bound: 200.0,
-            maximum_distance: 280.0,
-            ..default()
-        }
-        .into(), */
-        ..default()
-    });
+    // a table model takes the place of the plane
+    if scene_models.table.is_some() {
+        return;
+    }
 
-    commands.spawn(PointLightBundle {
-        point_light: PointLight {
-            intensity: 9000.0,
-            range: 200.,
-            shadows_enabled: true,
+    // plane
+    commands.spawn((
+        PbrBundle {
+            mesh: meshes.add(shape::Plane::from_size(200.0).into()),
+            material: materials.add(StandardMaterial {
+                base_color: Color::SILVER,
+                perceptual_roughness: 1.0,
+                ..default()
+            }),
+            transform: Transform::from_xyz(10.0, GROUND_HEIGHT, 0.0),
             ..default()
         },
-        transform: Transform::from_xyz(8.0, 16.0, 8.0),
-        ..default()
-    });
+        RigidBody::Fixed,
+        Collider::cuboid(100.0, 0.1, 100.0),
+        ground_groups(),
+    ));
 }
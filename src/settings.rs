@@ -0,0 +1,904 @@
+use crate::{
+    actions::{Action, ActionState, InputBinding, InputMap},
+    camera::{CameraMode, CameraView, SelectCameraView},
+    contacts::ContactSettings,
+    cradle::Ball,
+    environment::{CurrentEnvironment, Environments},
+    forces::ForceSettings,
+    graphics::{GraphicsPreset, GraphicsSettings, MsaaSamples},
+    history::RecordHistory,
+    launcher::{LaunchOrigin, LauncherSettings},
+    lighting::{LightingSettings, TimeOfDay},
+    loading::FontAssets,
+    materials::{BallFinish, BallFinishes, EditedBall},
+    menu::ButtonColors,
+    trails::TrailSettings,
+};
+use bevy::{
+    ecs::system::SystemParam,
+    input::mouse::{MouseScrollUnit, MouseWheel},
+    prelude::*,
+};
+
+pub struct SettingsPlugin;
+
+/// This plugin draws the settings panel on top of the menu or the running game
+/// Gameplay input is suspended while the panel is open
+impl Plugin for SettingsPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(SettingsPanel::default())
+            .insert_resource(RebindState::default())
+            .insert_resource(SettingsScroll::default())
+            .add_systems(
+                Update,
+                (
+                    capture_rebinding,
+                    toggle_settings_panel,
+                    handle_settings_buttons,
+                    rebuild_settings_panel,
+                    scroll_settings_panel,
+                )
+                    .chain()
+                    .run_if(resource_exists::<FontAssets>()),
+            );
+    }
+}
+
+#[derive(Resource, Default)]
+pub struct SettingsPanel {
+    pub open: bool,
+    pub tab: SettingsTab,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum SettingsTab {
+    #[default]
+    Controls,
+    Launcher,
+    Camera,
+    Environment,
+    Lighting,
+    Graphics,
+    Balls,
+    Overlays,
+}
+
+impl SettingsTab {
+    const ALL: [SettingsTab; 8] = [
+        SettingsTab::Controls,
+        SettingsTab::Launcher,
+        SettingsTab::Camera,
+        SettingsTab::Environment,
+        SettingsTab::Lighting,
+        SettingsTab::Graphics,
+        SettingsTab::Balls,
+        SettingsTab::Overlays,
+    ];
+
+    fn label(&self) -> &'static str {
+        match self {
+            SettingsTab::Controls => "Controls",
+            SettingsTab::Launcher => "Launcher",
+            SettingsTab::Camera => "Camera",
+            SettingsTab::Environment => "Sky",
+            SettingsTab::Lighting => "Light",
+            SettingsTab::Graphics => "Graphics",
+            SettingsTab::Balls => "Balls",
+            SettingsTab::Overlays => "Overlays",
+        }
+    }
+}
+
+/// A value shown in the panel with buttons to change it
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Parameter {
+    LaunchOrigin,
+    ProjectileMass,
+    ProjectileSpeed,
+    ProjectileRestitution,
+    ProjectileRadius,
+    LightingPreset,
+    DayCycle,
+    DayLength,
+    TimeOfDay,
+    GraphicsPreset,
+    Msaa,
+    Shadows,
+    ShadowMapSize,
+    ShadowCascades,
+    Fog,
+    Tonemapping,
+    SphereDetail,
+    BallColor,
+    Metallic,
+    Roughness,
+    Emissive,
+    BallTexture,
+    Trails,
+    TrailLength,
+    Contacts,
+    ContactFade,
+    Forces,
+}
+
+impl Parameter {
+    /// Changes to these are undone with the cradle, the others only change the view
+    fn is_cradle_state(&self) -> bool {
+        matches!(
+            self,
+            Parameter::LaunchOrigin
+                | Parameter::ProjectileMass
+                | Parameter::ProjectileSpeed
+                | Parameter::ProjectileRestitution
+                | Parameter::ProjectileRadius
+                | Parameter::BallColor
+                | Parameter::Metallic
+                | Parameter::Roughness
+                | Parameter::Emissive
+                | Parameter::BallTexture
+        )
+    }
+}
+
+/// Everything the parameter rows read and change
+#[derive(SystemParam)]
+struct Parameters<'w> {
+    launcher: ResMut<'w, LauncherSettings>,
+    lighting: ResMut<'w, LightingSettings>,
+    time_of_day: ResMut<'w, TimeOfDay>,
+    graphics: ResMut<'w, GraphicsSettings>,
+    ball_finishes: ResMut<'w, BallFinishes>,
+    edited_ball: Res<'w, EditedBall>,
+    trails: ResMut<'w, TrailSettings>,
+    contacts: ResMut<'w, ContactSettings>,
+    forces: ResMut<'w, ForceSettings>,
+}
+
+impl Parameters<'_> {
+    /// The time of day is left out, it changes every frame during the day cycle
+    fn is_changed(&self) -> bool {
+        self.launcher.is_changed()
+            || self.lighting.is_changed()
+            || self.graphics.is_changed()
+            || self.ball_finishes.is_changed()
+            || self.edited_ball.is_changed()
+            || self.trails.is_changed()
+            || self.contacts.is_changed()
+            || self.forces.is_changed()
+    }
+
+    fn label(&self, parameter: Parameter) -> String {
+        match parameter {
+            Parameter::LaunchOrigin => format!("Origin: {:?}", self.launcher.origin),
+            Parameter::ProjectileMass => format!("Mass: {:.1}", self.launcher.mass),
+            Parameter::ProjectileSpeed => format!("Speed: {:.0}", self.launcher.speed),
+            Parameter::ProjectileRestitution => {
+                format!("Restitution: {:.2}", self.launcher.restitution)
+            }
+            Parameter::ProjectileRadius => format!("Radius: {:.2}", self.launcher.radius),
+            Parameter::LightingPreset => format!("Preset: {}", self.lighting.preset.label()),
+            Parameter::DayCycle => format!("Day cycle: {}", on_off(self.lighting.day_cycle)),
+            Parameter::DayLength => format!("Day length: {:.0}s", self.lighting.day_length),
+            Parameter::TimeOfDay => {
+                let hours = self.time_of_day.0 * 24.0;
+                format!(
+                    "Time: {:02}:{:02}",
+                    hours as u32,
+                    (hours.fract() * 60.0) as u32
+                )
+            }
+            Parameter::GraphicsPreset => format!("Quality: {:?}", self.graphics.preset),
+            Parameter::Msaa => format!(
+                "MSAA: {}",
+                match self.graphics.msaa {
+                    MsaaSamples::Off => "off",
+                    MsaaSamples::Sample2 => "2x",
+                    MsaaSamples::Sample4 => "4x",
+                    MsaaSamples::Sample8 => "8x",
+                }
+            ),
+            Parameter::Shadows => format!("Shadows: {}", on_off(self.graphics.shadows)),
+            Parameter::ShadowMapSize => format!("Shadow map: {}", self.graphics.shadow_map_size),
+            Parameter::ShadowCascades => {
+                format!("Shadow cascades: {}", self.graphics.shadow_cascades)
+            }
+            Parameter::Fog => format!("Fog: {}", on_off(self.graphics.fog)),
+            Parameter::Tonemapping => format!("Tonemapping: {:?}", self.graphics.tonemapping),
+            Parameter::SphereDetail => format!("Sphere detail: {}", self.graphics.sphere_detail),
+            Parameter::BallColor => format!("Color: {}", self.ball_finish().color_name()),
+            Parameter::Metallic => format!("Metallic: {:.1}", self.ball_finish().metallic),
+            Parameter::Roughness => format!("Roughness: {:.2}", self.ball_finish().roughness),
+            Parameter::Emissive => format!("Glow: {:.1}", self.ball_finish().emissive),
+            Parameter::BallTexture => format!("Texture: {:?}", self.ball_finish().texture),
+            Parameter::Trails => format!("Trails: {}", on_off(self.trails.visible)),
+            Parameter::TrailLength => format!("Trail length: {:.1}s", self.trails.length),
+            Parameter::Contacts => format!("Contacts: {}", on_off(self.contacts.visible)),
+            Parameter::ContactFade => format!("Contact fade: {:.1}s", self.contacts.fade),
+            Parameter::Forces => format!("Forces: {}", on_off(self.forces.visible)),
+        }
+    }
+
+    /// Moves the value one step up or down, `direction` is `1.0` or `-1.0`
+    fn step(&mut self, parameter: Parameter, direction: f32) {
+        match parameter {
+            Parameter::LaunchOrigin => {
+                self.launcher.origin = match self.launcher.origin {
+                    LaunchOrigin::Camera => LaunchOrigin::Cannon,
+                    LaunchOrigin::Cannon => LaunchOrigin::Camera,
+                };
+            }
+            Parameter::ProjectileMass => {
+                self.launcher.mass = (self.launcher.mass + direction).clamp(1.0, 50.0);
+            }
+            Parameter::ProjectileSpeed => {
+                self.launcher.speed = (self.launcher.speed + direction * 5.0).clamp(5.0, 100.0);
+            }
+            Parameter::ProjectileRestitution => {
+                self.launcher.restitution =
+                    (self.launcher.restitution + direction * 0.05).clamp(0.0, 1.0);
+            }
+            Parameter::ProjectileRadius => {
+                self.launcher.radius = (self.launcher.radius + direction * 0.25).clamp(0.25, 3.0);
+            }
+            Parameter::LightingPreset => {
+                self.lighting.preset = self.lighting.preset.next(direction as i32);
+            }
+            Parameter::DayCycle => self.lighting.day_cycle = !self.lighting.day_cycle,
+            Parameter::DayLength => {
+                self.lighting.day_length =
+                    (self.lighting.day_length + direction * 30.0).clamp(30.0, 600.0);
+            }
+            Parameter::TimeOfDay => {
+                self.time_of_day.0 = (self.time_of_day.0 + direction / 24.0).rem_euclid(1.0);
+                // the panel does not watch the time of day, show the new time anyway
+                self.lighting.set_changed();
+            }
+            Parameter::GraphicsPreset => {
+                *self.graphics = self.graphics.preset.next(direction as i32).settings();
+            }
+            Parameter::BallColor => {
+                self.ball_finishes.edit(self.edited_ball.index, |finish| {
+                    finish.next_color(direction as i32);
+                });
+            }
+            Parameter::Metallic => {
+                self.ball_finishes.edit(self.edited_ball.index, |finish| {
+                    finish.metallic = (finish.metallic + direction * 0.1).clamp(0.0, 1.0);
+                });
+            }
+            Parameter::Roughness => {
+                self.ball_finishes.edit(self.edited_ball.index, |finish| {
+                    finish.roughness = (finish.roughness + direction * 0.05).clamp(0.0, 1.0);
+                });
+            }
+            Parameter::Emissive => {
+                self.ball_finishes.edit(self.edited_ball.index, |finish| {
+                    finish.emissive = (finish.emissive + direction * 0.5).clamp(0.0, 10.0);
+                });
+            }
+            Parameter::BallTexture => {
+                self.ball_finishes.edit(self.edited_ball.index, |finish| {
+                    finish.texture = finish.texture.next();
+                });
+            }
+            Parameter::Trails => self.trails.visible = !self.trails.visible,
+            Parameter::TrailLength => {
+                self.trails.length = (self.trails.length + direction * 0.5).clamp(0.5, 10.0);
+            }
+            Parameter::Contacts => self.contacts.visible = !self.contacts.visible,
+            Parameter::ContactFade => {
+                self.contacts.fade = (self.contacts.fade + direction * 0.5).clamp(0.5, 5.0);
+            }
+            Parameter::Forces => self.forces.visible = !self.forces.visible,
+            Parameter::Msaa
+            | Parameter::Shadows
+            | Parameter::ShadowMapSize
+            | Parameter::ShadowCascades
+            | Parameter::Fog
+            | Parameter::Tonemapping
+            | Parameter::SphereDetail => {
+                self.step_graphics(parameter, direction);
+                self.graphics.preset = GraphicsPreset::Custom;
+            }
+        }
+    }
+
+    fn ball_finish(&self) -> &BallFinish {
+        self.ball_finishes.finish(self.edited_ball.index)
+    }
+
+    /// The graphics settings picked one by one, they no longer match a preset
+    fn step_graphics(&mut self, parameter: Parameter, direction: f32) {
+        let graphics = &mut *self.graphics;
+        match parameter {
+            Parameter::Msaa => graphics.msaa = graphics.msaa.next(direction as i32),
+            Parameter::Shadows => graphics.shadows = !graphics.shadows,
+            Parameter::ShadowMapSize => {
+                graphics.shadow_map_size = if direction > 0.0 {
+                    (graphics.shadow_map_size * 2).min(8192)
+                } else {
+                    (graphics.shadow_map_size / 2).max(512)
+                };
+            }
+            Parameter::ShadowCascades => {
+                graphics.shadow_cascades =
+                    (graphics.shadow_cascades as i32 + direction as i32).clamp(1, 4) as usize;
+            }
+            Parameter::Fog => graphics.fog = !graphics.fog,
+            Parameter::Tonemapping => {
+                graphics.tonemapping = graphics.tonemapping.next(direction as i32);
+            }
+            Parameter::SphereDetail => {
+                graphics.sphere_detail =
+                    (graphics.sphere_detail as i32 + direction as i32 * 6).clamp(6, 64) as usize;
+            }
+            _ => {}
+        }
+    }
+}
+
+/// The binding waiting for the next key, button or touch
+#[derive(Resource, Default)]
+struct RebindState {
+    target: Option<RebindTarget>,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum RebindTarget {
+    /// index into [`InputMap::bindings`]
+    Replace(usize),
+    Add(Action),
+}
+
+/// How far the panel content is scrolled up, kept apart from [`SettingsPanel`],
+/// so scrolling does not rebuild the panel
+#[derive(Resource, Default)]
+struct SettingsScroll {
+    offset: f32,
+}
+
+#[derive(Component)]
+struct SettingsRoot;
+
+/// The clipped area below the tabs
+#[derive(Component)]
+struct SettingsViewport;
+
+/// Everything in the viewport, moved up to scroll
+#[derive(Component)]
+struct SettingsContent;
+
+/// Keys that make a chord with the key pressed after them
+const MODIFIER_KEYS: [KeyCode; 8] = [
+    KeyCode::ControlLeft,
+    KeyCode::ControlRight,
+    KeyCode::ShiftLeft,
+    KeyCode::ShiftRight,
+    KeyCode::AltLeft,
+    KeyCode::AltRight,
+    KeyCode::SuperLeft,
+    KeyCode::SuperRight,
+];
+
+#[derive(Component, Clone, Copy)]
+enum SettingsButton {
+    Tab(SettingsTab),
+    Binding(usize),
+    AddBinding(Action),
+    DefaultBindings,
+    Step(Parameter, f32),
+    CameraView(CameraView),
+    CameraMode(i32),
+    EditedBall(i32),
+    Environment(usize),
+    Close,
+}
+
+fn capture_rebinding(
+    mut rebind_state: ResMut<RebindState>,
+    mut input_map: ResMut<InputMap>,
+    mut action_state: ResMut<ActionState>,
+    keys: Res<Input<KeyCode>>,
+    mouse_buttons: Res<Input<MouseButton>>,
+    gamepad_buttons: Res<Input<GamepadButton>>,
+    touches: Res<Touches>,
+) {
+    let Some(target) = rebind_state.target else {
+        return;
+    };
+
+    let held_modifier = MODIFIER_KEYS.into_iter().find(|key| keys.pressed(*key));
+    let binding = if let Some(key) = keys
+        .get_just_pressed()
+        .find(|key| !MODIFIER_KEYS.contains(key))
+    {
+        match held_modifier {
+            Some(modifier) => InputBinding::Chord(modifier, *key),
+            None => InputBinding::Key(*key),
+        }
+    } else if let Some(modifier) = keys
+        .get_just_released()
+        .find(|key| MODIFIER_KEYS.contains(key))
+    {
+        // a modifier pressed and let go on its own is bound by itself
+        InputBinding::Key(*modifier)
+    } else if let Some(button) = mouse_buttons.get_just_pressed().next() {
+        InputBinding::Mouse(*button)
+    } else if let Some(button) = gamepad_buttons.get_just_pressed().next() {
+        InputBinding::Gamepad(button.button_type)
+    } else if touches.any_just_pressed() {
+        InputBinding::Touch
+    } else {
+        return;
+    };
+
+    match (binding, target) {
+        // escape cancels
+        (InputBinding::Key(KeyCode::Escape) | InputBinding::Chord(_, KeyCode::Escape), _) => {}
+        // backspace or delete remove the binding
+        (InputBinding::Key(KeyCode::Back | KeyCode::Delete), RebindTarget::Replace(index)) => {
+            if index < input_map.bindings.len() {
+                input_map.bindings.remove(index);
+            }
+        }
+        (_, RebindTarget::Replace(index)) => {
+            if let Some((_, old_binding)) = input_map.bindings.get_mut(index) {
+                *old_binding = binding;
+            }
+        }
+        (_, RebindTarget::Add(action)) => {
+            if !input_map.is_bound(action, binding) {
+                input_map.bindings.push((action, binding));
+            }
+        }
+    }
+    rebind_state.target = None;
+
+    // the input that ended the capture should not trigger anything else
+    for (action, bound) in &input_map.bindings {
+        if *bound == binding {
+            action_state.consume(*action);
+        }
+    }
+}
+
+fn toggle_settings_panel(
+    mut settings_panel: ResMut<SettingsPanel>,
+    mut rebind_state: ResMut<RebindState>,
+    mut action_state: ResMut<ActionState>,
+) {
+    if action_state.just_pressed(Action::ToggleSettings) {
+        settings_panel.open = !settings_panel.open;
+    }
+    if settings_panel.is_changed() {
+        if !settings_panel.open {
+            rebind_state.target = None;
+        }
+        action_state.suspend_gameplay = settings_panel.open;
+    }
+}
+
+fn handle_settings_buttons(
+    mut settings_panel: ResMut<SettingsPanel>,
+    mut rebind_state: ResMut<RebindState>,
+    mut input_map: ResMut<InputMap>,
+    mut parameters: Parameters,
+    mut history: EventWriter<RecordHistory>,
+    mut camera_views: EventWriter<SelectCameraView>,
+    mut camera_mode: ResMut<CameraMode>,
+    mut current_environment: ResMut<CurrentEnvironment>,
+    mut edited_ball: ResMut<EditedBall>,
+    balls: Query<&Ball>,
+    interaction_query: Query<(&Interaction, &SettingsButton), Changed<Interaction>>,
+) {
+    for (interaction, button) in &interaction_query {
+        if *interaction != Interaction::Pressed {
+            continue;
+        }
+        match *button {
+            SettingsButton::Tab(tab) => settings_panel.tab = tab,
+            SettingsButton::Binding(index) => {
+                rebind_state.target = Some(RebindTarget::Replace(index));
+            }
+            SettingsButton::AddBinding(action) => {
+                rebind_state.target = Some(RebindTarget::Add(action));
+            }
+            SettingsButton::DefaultBindings => {
+                *input_map = InputMap::default();
+                rebind_state.target = None;
+            }
+            SettingsButton::Step(parameter, direction) => {
+                if parameter.is_cradle_state() {
+                    history.send(RecordHistory);
+                }
+                parameters.step(parameter, direction);
+            }
+            SettingsButton::CameraView(view) => camera_views.send(SelectCameraView(view)),
+            SettingsButton::CameraMode(direction) => {
+                *camera_mode = camera_mode.cycle(direction, balls.iter().count());
+            }
+            SettingsButton::EditedBall(direction) => {
+                edited_ball.cycle(direction, balls.iter().count());
+            }
+            SettingsButton::Environment(index) => current_environment.index = index,
+            SettingsButton::Close => settings_panel.open = false,
+        }
+    }
+}
+
+/// The panel is rebuilt from scratch whenever something it shows changes
+fn rebuild_settings_panel(
+    mut commands: Commands,
+    settings_panel: Res<SettingsPanel>,
+    mut settings_scroll: ResMut<SettingsScroll>,
+    rebind_state: Res<RebindState>,
+    input_map: Res<InputMap>,
+    parameters: Parameters,
+    camera_mode: Res<CameraMode>,
+    environments: Res<Environments>,
+    current_environment: Res<CurrentEnvironment>,
+    font_assets: Res<FontAssets>,
+    button_colors: Res<ButtonColors>,
+    roots: Query<Entity, With<SettingsRoot>>,
+) {
+    if !(settings_panel.is_changed()
+        || rebind_state.is_changed()
+        || input_map.is_changed()
+        || parameters.is_changed()
+        || camera_mode.is_changed()
+        || current_environment.is_changed())
+    {
+        return;
+    }
+
+    for root in &roots {
+        commands.entity(root).despawn_recursive();
+    }
+    if !settings_panel.open {
+        return;
+    }
+    // stay at the same row while changing values, start at the top of another tab
+    if settings_panel.is_changed() {
+        settings_scroll.offset = 0.0;
+    }
+
+    let ui = PanelStyle {
+        font: font_assets.fira_sans.clone(),
+        button_color: button_colors.normal,
+    };
+
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    right: Val::Px(10.0),
+                    top: Val::Px(10.0),
+                    width: Val::Px(380.0),
+                    max_height: Val::Percent(95.0),
+                    flex_direction: FlexDirection::Column,
+                    padding: UiRect::all(Val::Px(8.0)),
+                    ..Default::default()
+                },
+                background_color: Color::rgba(0.05, 0.05, 0.05, 0.9).into(),
+                z_index: ZIndex::Global(10),
+                ..Default::default()
+            },
+            SettingsRoot,
+        ))
+        .with_children(|parent| {
+            parent.spawn(row()).with_children(|parent| {
+                for tab in SettingsTab::ALL {
+                    ui.button(parent, tab.label(), SettingsButton::Tab(tab));
+                }
+                ui.button(parent, "Close", SettingsButton::Close);
+            });
+
+            parent
+                .spawn((
+                    NodeBundle {
+                        style: Style {
+                            flex_direction: FlexDirection::Column,
+                            // shrink below the content, the rest is scrolled to
+                            min_height: Val::Px(0.0),
+                            overflow: Overflow::clip_y(),
+                            ..Default::default()
+                        },
+                        ..Default::default()
+                    },
+                    SettingsViewport,
+                ))
+                .with_children(|parent| {
+                    parent
+                        .spawn((
+                            NodeBundle {
+                                style: Style {
+                                    flex_direction: FlexDirection::Column,
+                                    top: Val::Px(-settings_scroll.offset),
+                                    ..Default::default()
+                                },
+                                ..Default::default()
+                            },
+                            SettingsContent,
+                        ))
+                        .with_children(|parent| match settings_panel.tab {
+                            SettingsTab::Controls => {
+                                build_controls_tab(parent, &ui, &input_map, rebind_state.target);
+                            }
+                            SettingsTab::Launcher => build_parameter_rows(
+                                parent,
+                                &ui,
+                                &parameters,
+                                &[
+                                    Parameter::LaunchOrigin,
+                                    Parameter::ProjectileMass,
+                                    Parameter::ProjectileSpeed,
+                                    Parameter::ProjectileRestitution,
+                                    Parameter::ProjectileRadius,
+                                ],
+                            ),
+                            SettingsTab::Camera => {
+                                build_camera_tab(parent, &ui, &input_map, *camera_mode)
+                            }
+                            SettingsTab::Lighting => build_parameter_rows(
+                                parent,
+                                &ui,
+                                &parameters,
+                                &[
+                                    Parameter::LightingPreset,
+                                    Parameter::DayCycle,
+                                    Parameter::DayLength,
+                                    Parameter::TimeOfDay,
+                                ],
+                            ),
+                            SettingsTab::Graphics => build_parameter_rows(
+                                parent,
+                                &ui,
+                                &parameters,
+                                &[
+                                    Parameter::GraphicsPreset,
+                                    Parameter::Msaa,
+                                    Parameter::Shadows,
+                                    Parameter::ShadowMapSize,
+                                    Parameter::ShadowCascades,
+                                    Parameter::Fog,
+                                    Parameter::Tonemapping,
+                                    Parameter::SphereDetail,
+                                ],
+                            ),
+                            SettingsTab::Balls => build_balls_tab(parent, &ui, &parameters),
+                            SettingsTab::Overlays => build_parameter_rows(
+                                parent,
+                                &ui,
+                                &parameters,
+                                &[
+                                    Parameter::Trails,
+                                    Parameter::TrailLength,
+                                    Parameter::Contacts,
+                                    Parameter::ContactFade,
+                                    Parameter::Forces,
+                                ],
+                            ),
+                            SettingsTab::Environment => {
+                                for (index, environment) in environments.list.iter().enumerate() {
+                                    parent.spawn(row()).with_children(|parent| {
+                                        let marker = if index == current_environment.index {
+                                            ">"
+                                        } else {
+                                            ""
+                                        };
+                                        ui.label(parent, marker, 20.0);
+                                        ui.button(
+                                            parent,
+                                            &environment.name,
+                                            SettingsButton::Environment(index),
+                                        );
+                                    });
+                                }
+                            }
+                        });
+                });
+        });
+}
+
+/// Scrolls the panel content with the mouse wheel or a dragging finger
+fn scroll_settings_panel(
+    settings_panel: Res<SettingsPanel>,
+    mut settings_scroll: ResMut<SettingsScroll>,
+    mut wheel_evr: EventReader<MouseWheel>,
+    touches: Res<Touches>,
+    viewports: Query<&Node, With<SettingsViewport>>,
+    mut contents: Query<(&Node, &mut Style), With<SettingsContent>>,
+) {
+    let wheel: f32 = wheel_evr
+        .iter()
+        .map(|ev| match ev.unit {
+            MouseScrollUnit::Line => ev.y * 20.0,
+            MouseScrollUnit::Pixel => ev.y,
+        })
+        .sum();
+    if !settings_panel.open {
+        return;
+    }
+    let dragged: f32 = touches.iter().map(|touch| touch.delta().y).sum();
+
+    let (Ok(viewport), Ok((content, mut style))) =
+        (viewports.get_single(), contents.get_single_mut())
+    else {
+        return;
+    };
+    let max_offset = (content.size().y - viewport.size().y).max(0.0);
+    let offset = (settings_scroll.offset - wheel - dragged).clamp(0.0, max_offset);
+    if offset != settings_scroll.offset {
+        settings_scroll.offset = offset;
+    }
+    let top = Val::Px(-offset);
+    if style.top != top {
+        style.top = top;
+    }
+}
+
+fn build_controls_tab(
+    parent: &mut ChildBuilder,
+    ui: &PanelStyle,
+    input_map: &InputMap,
+    rebind_target: Option<RebindTarget>,
+) {
+    for action in Action::all() {
+        parent.spawn(row()).with_children(|parent| {
+            ui.label(parent, &action.label(), 120.0);
+            for (index, (_, binding)) in input_map
+                .bindings
+                .iter()
+                .enumerate()
+                .filter(|(_, (bound_action, _))| *bound_action == action)
+            {
+                let label = if rebind_target == Some(RebindTarget::Replace(index)) {
+                    "...".to_string()
+                } else {
+                    binding.label()
+                };
+                ui.button(parent, &label, SettingsButton::Binding(index));
+            }
+            let add_label = if rebind_target == Some(RebindTarget::Add(action)) {
+                "..."
+            } else {
+                "+"
+            };
+            ui.button(parent, add_label, SettingsButton::AddBinding(action));
+        });
+    }
+
+    parent.spawn(row()).with_children(|parent| {
+        ui.label(parent, "Click a binding and press any input", 260.0);
+        ui.button(parent, "Defaults", SettingsButton::DefaultBindings);
+    });
+}
+
+fn build_camera_tab(
+    parent: &mut ChildBuilder,
+    ui: &PanelStyle,
+    input_map: &InputMap,
+    camera_mode: CameraMode,
+) {
+    parent.spawn(row()).with_children(|parent| {
+        ui.label(parent, &format!("Mode: {}", camera_mode.label()), 200.0);
+        ui.button(parent, "-", SettingsButton::CameraMode(-1));
+        ui.button(parent, "+", SettingsButton::CameraMode(1));
+    });
+    for view in CameraView::ALL {
+        parent.spawn(row()).with_children(|parent| {
+            let keys: Vec<_> = input_map
+                .bindings
+                .iter()
+                .filter(|(action, _)| *action == Action::CameraView(view))
+                .map(|(_, binding)| binding.label())
+                .collect();
+            ui.label(parent, &keys.join(", "), 200.0);
+            ui.button(parent, view.label(), SettingsButton::CameraView(view));
+        });
+    }
+}
+
+fn build_balls_tab(parent: &mut ChildBuilder, ui: &PanelStyle, parameters: &Parameters) {
+    let edited = match parameters.edited_ball.index {
+        Some(index) => format!("Ball {}", index + 1),
+        None => "All balls".to_string(),
+    };
+    parent.spawn(row()).with_children(|parent| {
+        ui.label(parent, &edited, 200.0);
+        ui.button(parent, "-", SettingsButton::EditedBall(-1));
+        ui.button(parent, "+", SettingsButton::EditedBall(1));
+    });
+    build_parameter_rows(
+        parent,
+        ui,
+        parameters,
+        &[
+            Parameter::BallColor,
+            Parameter::Metallic,
+            Parameter::Roughness,
+            Parameter::Emissive,
+            Parameter::BallTexture,
+        ],
+    );
+}
+
+fn build_parameter_rows(
+    parent: &mut ChildBuilder,
+    ui: &PanelStyle,
+    parameters: &Parameters,
+    rows: &[Parameter],
+) {
+    for parameter in rows {
+        parent.spawn(row()).with_children(|parent| {
+            ui.label(parent, &parameters.label(*parameter), 200.0);
+            ui.button(parent, "-", SettingsButton::Step(*parameter, -1.0));
+            ui.button(parent, "+", SettingsButton::Step(*parameter, 1.0));
+        });
+    }
+}
+
+fn on_off(value: bool) -> &'static str {
+    if value {
+        "on"
+    } else {
+        "off"
+    }
+}
+
+fn row() -> NodeBundle {
+    NodeBundle {
+        style: Style {
+            flex_direction: FlexDirection::Row,
+            flex_wrap: FlexWrap::Wrap,
+            align_items: AlignItems::Center,
+            margin: UiRect::bottom(Val::Px(2.0)),
+            ..Default::default()
+        },
+        ..Default::default()
+    }
+}
+
+struct PanelStyle {
+    font: Handle<Font>,
+    button_color: Color,
+}
+
+impl PanelStyle {
+    fn text_style(&self) -> TextStyle {
+        TextStyle {
+            font: self.font.clone(),
+            font_size: 16.0,
+            color: Color::rgb(0.9, 0.9, 0.9),
+        }
+    }
+
+    fn label(&self, parent: &mut ChildBuilder, text: &str, width: f32) {
+        parent.spawn(
+            TextBundle::from_section(text, self.text_style()).with_style(Style {
+                width: Val::Px(width),
+                ..Default::default()
+            }),
+        );
+    }
+
+    fn button(&self, parent: &mut ChildBuilder, text: &str, button: SettingsButton) {
+        parent
+            .spawn((
+                ButtonBundle {
+                    style: Style {
+                        padding: UiRect::axes(Val::Px(6.0), Val::Px(2.0)),
+                        margin: UiRect::all(Val::Px(2.0)),
+                        ..Default::default()
+                    },
+                    background_color: self.button_color.into(),
+                    ..Default::default()
+                },
+                button,
+            ))
+            .with_children(|parent| {
+                parent.spawn(TextBundle::from_section(text, self.text_style()));
+            });
+    }
+}
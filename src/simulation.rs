@@ -0,0 +1,59 @@
+use crate::{
+    actions::{Action, ActionState},
+    GameState,
+};
+use bevy::prelude::*;
+use bevy_rapier3d::prelude::*;
+
+pub struct SimulationPlugin;
+
+/// This plugin pauses the physics and steps through it frame by frame
+impl Plugin for SimulationPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(SimulationControl::default())
+            .add_systems(
+                Update,
+                (
+                    handle_simulation_actions.run_if(in_state(GameState::Playing)),
+                    apply_simulation_control,
+                )
+                    .chain(),
+            );
+    }
+}
+
+#[derive(Resource, Default)]
+pub struct SimulationControl {
+    pub paused: bool,
+    step_requested: bool,
+}
+
+impl SimulationControl {
+    /// Runs a single physics step on the next frame while paused
+    pub fn step(&mut self) {
+        self.step_requested = true;
+    }
+}
+
+fn handle_simulation_actions(
+    actions: Res<ActionState>,
+    mut simulation_control: ResMut<SimulationControl>,
+) {
+    if actions.just_pressed(Action::Pause) {
+        simulation_control.paused = !simulation_control.paused;
+    }
+    if actions.just_pressed(Action::StepFrame) && simulation_control.paused {
+        simulation_control.step();
+    }
+}
+
+fn apply_simulation_control(
+    mut simulation_control: ResMut<SimulationControl>,
+    mut rapier_config: ResMut<RapierConfiguration>,
+) {
+    let step = std::mem::take(&mut simulation_control.step_requested);
+    let active = !simulation_control.paused || step;
+    if rapier_config.physics_pipeline_active != active {
+        rapier_config.physics_pipeline_active = active;
+    }
+}
@@ -0,0 +1,119 @@
+use std::collections::VecDeque;
+
+use crate::{
+    actions::{Action, ActionState},
+    cradle::Ball,
+    GameState,
+};
+use bevy::prelude::*;
+use bevy_rapier3d::prelude::*;
+
+pub struct TrailsPlugin;
+
+/// This plugin draws a fading line behind every ball along its recent path,
+/// colored from blue when slow to red when fast
+impl Plugin for TrailsPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(TrailSettings::default()).add_systems(
+            Update,
+            (
+                toggle_trails.run_if(in_state(GameState::Playing)),
+                attach_trails.run_if(in_state(GameState::Playing)),
+                record_trails.run_if(in_state(GameState::Playing)),
+                draw_trails.run_if(in_state(GameState::Playing)),
+            )
+                .chain(),
+        );
+    }
+}
+
+#[derive(Resource, Clone, Debug, PartialEq)]
+pub struct TrailSettings {
+    pub visible: bool,
+    /// seconds of the path kept behind a ball
+    pub length: f32,
+    /// balls at this speed or faster get the hottest color
+    pub max_speed: f32,
+}
+
+impl Default for TrailSettings {
+    fn default() -> Self {
+        TrailSettings {
+            visible: false,
+            length: 1.5,
+            max_speed: 20.0,
+        }
+    }
+}
+
+struct TrailPoint {
+    time: f32,
+    position: Vec3,
+    speed: f32,
+}
+
+/// Where a ball has been lately, oldest first
+#[derive(Component, Default)]
+struct Trail {
+    points: VecDeque<TrailPoint>,
+}
+
+fn toggle_trails(actions: Res<ActionState>, mut trail_settings: ResMut<TrailSettings>) {
+    if actions.just_pressed(Action::ToggleTrails) {
+        trail_settings.visible = !trail_settings.visible;
+    }
+}
+
+fn attach_trails(mut commands: Commands, balls: Query<Entity, Added<Ball>>) {
+    for entity in &balls {
+        commands.entity(entity).insert(Trail::default());
+    }
+}
+
+fn record_trails(
+    time: Res<Time>,
+    trail_settings: Res<TrailSettings>,
+    mut trails: Query<(&mut Trail, &GlobalTransform, &Velocity)>,
+) {
+    let now = time.elapsed_seconds();
+    for (mut trail, transform, velocity) in &mut trails {
+        if !trail_settings.visible {
+            // start from the ball when shown again, not from where it was back then
+            trail.points.clear();
+            continue;
+        }
+        trail.points.push_back(TrailPoint {
+            time: now,
+            position: transform.translation(),
+            speed: velocity.linvel.length(),
+        });
+        while trail
+            .points
+            .front()
+            .is_some_and(|point| now - point.time > trail_settings.length)
+        {
+            trail.points.pop_front();
+        }
+    }
+}
+
+fn draw_trails(
+    time: Res<Time>,
+    trail_settings: Res<TrailSettings>,
+    trails: Query<&Trail>,
+    mut gizmos: Gizmos,
+) {
+    if !trail_settings.visible {
+        return;
+    }
+    let now = time.elapsed_seconds();
+    for trail in &trails {
+        gizmos.linestrip_gradient(trail.points.iter().map(|point| {
+            let age = ((now - point.time) / trail_settings.length).clamp(0.0, 1.0);
+            let heat = (point.speed / trail_settings.max_speed).clamp(0.0, 1.0);
+            // hue 240 is blue, 0 is red
+            let color = Color::hsla(240.0 * (1.0 - heat), 1.0, 0.5, 1.0 - age);
+            (point.position, color)
+        }));
+    }
+}